@@ -0,0 +1,184 @@
+use super::LrxFile;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Thresholds for timing analysis
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimingOptions {
+    /// Reading speed above which a line is considered too brief for its length
+    pub max_chars_per_second: f64,
+    /// Lines displayed for less than this (seconds) are considered crammed together
+    pub min_line_duration: f64,
+    /// Gaps between lines longer than this (seconds) are flagged as suspicious
+    pub max_gap: f64,
+}
+
+impl Default for TimingOptions {
+    fn default() -> Self {
+        Self {
+            max_chars_per_second: 20.0,
+            min_line_duration: 0.75,
+            max_gap: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TimingIssueKind {
+    /// Shown too briefly to read at a comfortable characters-per-second rate
+    TooFast { chars_per_second: f64 },
+    /// Shown for less than the minimum line duration
+    Crammed { duration: f64 },
+    /// Unusually long stretch without any lyric
+    LongGap { gap: f64 },
+    /// Timestamp before the start of the song
+    BeforeStart,
+    /// Timestamp after the end of the song
+    AfterEnd { song_duration: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimingIssue {
+    pub line_index: usize, // Index into LrxFile::lines
+    pub kind: TimingIssueKind,
+}
+
+impl TimingIssue {
+    pub fn message(&self) -> String {
+        match &self.kind {
+            TimingIssueKind::TooFast { chars_per_second } => {
+                format!("Too brief for its length ({:.1} chars/s)", chars_per_second)
+            }
+            TimingIssueKind::Crammed { duration } => {
+                format!("Shown for only {:.2}s", duration)
+            }
+            TimingIssueKind::LongGap { gap } => {
+                format!("No lyrics for {:.1}s after this line", gap)
+            }
+            TimingIssueKind::BeforeStart => "Timestamp is before 00:00".to_string(),
+            TimingIssueKind::AfterEnd { song_duration } => {
+                format!("Timestamp is after the end of the song ({:.1}s)", song_duration)
+            }
+        }
+    }
+}
+
+impl LrxFile {
+    /// Analyze lyric timing for readability problems
+    ///
+    /// `song_duration` is the audio length in seconds; when unknown the `length` tag is used.
+    /// An `end` trim point, if set, caps the song length.
+    /// Lines sharing a timestamp (parts singing together) are treated as one display step.
+    pub fn analyze_timing(&self, song_duration: Option<f64>, options: &TimingOptions) -> Vec<TimingIssue> {
+        let song_duration = song_duration.filter(|d| *d > 0.0).or_else(|| self.length());
+        // Lines after the [end:] trim point are never shown
+        let song_duration = match (self.end(), song_duration) {
+            (Some(end), Some(duration)) => Some(end.min(duration)),
+            (end, duration) => end.or(duration),
+        };
+        let mut issues = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.timestamp < 0.0 {
+                issues.push(TimingIssue { line_index: i, kind: TimingIssueKind::BeforeStart });
+            }
+
+            if let Some(end) = song_duration
+                && line.timestamp > end
+            {
+                issues.push(TimingIssue { line_index: i, kind: TimingIssueKind::AfterEnd { song_duration: end } });
+                continue;
+            }
+
+            // The line stays up until the next line with a later timestamp
+            let next_time = self.lines[i + 1..]
+                .iter()
+                .map(|l| l.timestamp)
+                .find(|t| *t > line.timestamp);
+
+            let display_end = match next_time.or(song_duration) {
+                Some(end) => end,
+                None => continue,
+            };
+            let duration = display_end - line.timestamp;
+
+            if let Some(next) = next_time {
+                let gap = next - line.timestamp;
+                if gap > options.max_gap {
+                    issues.push(TimingIssue { line_index: i, kind: TimingIssueKind::LongGap { gap } });
+                    continue;
+                }
+            }
+
+            let text = line.text.trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            if duration < options.min_line_duration {
+                issues.push(TimingIssue { line_index: i, kind: TimingIssueKind::Crammed { duration } });
+                continue;
+            }
+
+            let chars_per_second = text.chars().count() as f64 / duration;
+            if chars_per_second > options.max_chars_per_second {
+                issues.push(TimingIssue { line_index: i, kind: TimingIssueKind::TooFast { chars_per_second } });
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(content: &str, duration: Option<f64>) -> Vec<TimingIssue> {
+        LrxFile::parse(content).unwrap().analyze_timing(duration, &TimingOptions::default())
+    }
+
+    #[test]
+    fn test_well_timed_lines() {
+        let issues = analyze(
+            "[00:01.00][lead]Hello there\n\
+             [00:01.00][harmony]Hello there\n\
+             [00:04.00]General Kenobi\n",
+            Some(10.0),
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_too_fast_and_crammed() {
+        let issues = analyze(
+            "[00:01.00]This is a rather long line that nobody can read in a second\n\
+             [00:02.00]Short\n\
+             [00:02.30]Next\n\
+             [00:05.00]End\n",
+            Some(10.0),
+        );
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(issues[0].kind, TimingIssueKind::TooFast { .. }));
+        assert_eq!(issues[1].line_index, 1);
+        assert!(matches!(issues[1].kind, TimingIssueKind::Crammed { .. }));
+    }
+
+    #[test]
+    fn test_gap_and_bounds() {
+        let issues = analyze(
+            "[length:01:00]\n\
+             [00:01.00]Before the break\n\
+             [00:50.00]After the break\n\
+             [01:10.00]Past the end\n",
+            None,
+        );
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(issues[0].kind, TimingIssueKind::LongGap { .. }));
+        assert_eq!(issues[1].kind, TimingIssueKind::AfterEnd { song_duration: 60.0 });
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Context, Result};
+
+/// Plain sRGB color with alpha, independent of any UI toolkit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::from_rgb(255, 255, 255);
+    pub const BLACK: Color = Color::from_rgb(0, 0, 0);
+
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn r(&self) -> u8 {
+        self.r
+    }
+
+    pub fn g(&self) -> u8 {
+        self.g
+    }
+
+    pub fn b(&self) -> u8 {
+        self.b
+    }
+
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    /// Parse a hex color in `#RRGGBB` format
+    pub fn from_hex(s: &str) -> Result<Self> {
+        if !s.starts_with('#') || s.len() != 7 {
+            return Err(anyhow!("Invalid color format: {}. Expected #RRGGBB", s));
+        }
+
+        let r = u8::from_str_radix(&s[1..3], 16)
+            .with_context(|| format!("Invalid red component in color: {}", s))?;
+        let g = u8::from_str_radix(&s[3..5], 16)
+            .with_context(|| format!("Invalid green component in color: {}", s))?;
+        let b = u8::from_str_radix(&s[5..7], 16)
+            .with_context(|| format!("Invalid blue component in color: {}", s))?;
+
+        Ok(Color::from_rgb(r, g, b))
+    }
+
+    /// Format as `#RRGGBB` (alpha is not part of the LRX format)
+    pub fn to_hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+// Colors are written as "#RRGGBB" strings, matching the LRX file format
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Color::from_hex(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hex() {
+        let white = Color::from_hex("#FFFFFF").unwrap();
+        assert_eq!(white, Color::from_rgb(255, 255, 255));
+
+        let red = Color::from_hex("#FF0000").unwrap();
+        assert_eq!(red, Color::from_rgb(255, 0, 0));
+
+        assert!(Color::from_hex("FFFFFF").is_err());
+        assert!(Color::from_hex("#FFF").is_err());
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(Color::from_rgb(255, 255, 255).to_hex(), "#FFFFFF");
+        assert_eq!(Color::from_rgb(255, 0, 0).to_hex(), "#FF0000");
+    }
+}
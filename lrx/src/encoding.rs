@@ -0,0 +1,169 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Text read from an LRX/LRC file along with the encoding it was decoded from
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub content: String,
+    pub encoding: &'static Encoding,
+    pub had_bom: bool,
+}
+
+impl DecodedText {
+    /// True if the file is already plain UTF-8 (no BOM), so re-saving it would change nothing
+    pub fn is_utf8(&self) -> bool {
+        self.encoding == UTF_8 && !self.had_bom
+    }
+
+    /// Human readable name of the source encoding, e.g. "Shift_JIS" or "UTF-8 (BOM)"
+    pub fn encoding_name(&self) -> String {
+        if self.had_bom {
+            format!("{} (BOM)", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
+}
+
+/// Read a lyric file from disk, detecting and decoding its text encoding
+pub fn read_to_string(path: &Path) -> Result<DecodedText> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+
+    Ok(decode(&bytes))
+}
+
+/// Decode raw file bytes into a string
+///
+/// Detection order: byte order mark, BOM-less UTF-16, valid UTF-8, then a
+/// statistical guess among legacy encodings (Shift_JIS, GBK, windows-1252, ...).
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    // Byte order mark
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (content, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return DecodedText {
+            content: content.into_owned(),
+            encoding,
+            had_bom: true,
+        };
+    }
+
+    // UTF-16 without a BOM (common from some Windows editors)
+    if let Some(encoding) = sniff_utf16(bytes) {
+        let (content, _) = encoding.decode_without_bom_handling(bytes);
+        return DecodedText {
+            content: content.into_owned(),
+            encoding,
+            had_bom: false,
+        };
+    }
+
+    // Plain UTF-8
+    if let Ok(content) = std::str::from_utf8(bytes) {
+        return DecodedText {
+            content: content.to_string(),
+            encoding: UTF_8,
+            had_bom: false,
+        };
+    }
+
+    // Legacy encoding - let the detector guess from the byte statistics
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    let (content, _) = encoding.decode_without_bom_handling(bytes);
+
+    DecodedText {
+        content: content.into_owned(),
+        encoding,
+        had_bom: false,
+    }
+}
+
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    // Lyric files are dominated by ASCII brackets, digits and spaces, so
+    // UTF-16 text has a zero byte in most code units on one side. The other
+    // side has the odd one too, from characters like U+4E00 (一).
+    let sample = &bytes[..bytes.len().min(1024)];
+    let pairs = sample.len() / 2;
+    if pairs < 4 {
+        return None;
+    }
+
+    let even_zeros = sample.chunks_exact(2).filter(|pair| pair[0] == 0).count();
+    let odd_zeros = sample.chunks_exact(2).filter(|pair| pair[1] == 0).count();
+    let many = |zeros: usize| zeros * 10 > pairs * 4; // Over 40% of the code units
+    let few = |zeros: usize| zeros * 20 < pairs; // Under 5%
+
+    if many(odd_zeros) && few(even_zeros) {
+        Some(UTF_16LE)
+    } else if many(even_zeros) && few(odd_zeros) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf8() {
+        let decoded = decode("[ti:Song]\n[00:01.00]歌詞".as_bytes());
+        assert_eq!(decoded.content, "[ti:Song]\n[00:01.00]歌詞");
+        assert!(decoded.is_utf8());
+    }
+
+    #[test]
+    fn test_decode_utf8_bom() {
+        let decoded = decode(b"\xEF\xBB\xBF[ti:Song]");
+        assert_eq!(decoded.content, "[ti:Song]");
+        assert_eq!(decoded.encoding, UTF_8);
+        assert!(!decoded.is_utf8());
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let text = "[ti:Song]\n[00:01.00]Hello";
+
+        let mut le_bom = vec![0xFF, 0xFE];
+        le_bom.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        assert_eq!(decode(&le_bom).content, text);
+
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        let decoded = decode(&be);
+        assert_eq!(decoded.content, text);
+        assert_eq!(decoded.encoding, UTF_16BE);
+    }
+
+    #[test]
+    fn test_decode_utf16_with_cjk() {
+        // 一 (U+4E00) puts a zero byte on the "wrong" side as well
+        let text = "[ti:Ichiman]\n[00:01.00]Sing it 一人で\n[00:05.00]万回 love you\n[00:09.00]Ah ah ah\n";
+
+        let le: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let decoded = decode(&le);
+        assert_eq!(decoded.content, text);
+        assert_eq!(decoded.encoding, UTF_16LE);
+
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        let decoded = decode(&be);
+        assert_eq!(decoded.content, text);
+        assert_eq!(decoded.encoding, UTF_16BE);
+    }
+
+    #[test]
+    fn test_decode_legacy() {
+        let text = "[ti:さくら]\n[00:01.00]さくら さくら 弥生の空は 見渡す限り";
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+        let decoded = decode(&bytes);
+        assert_eq!(decoded.content, text);
+        assert_eq!(decoded.encoding, encoding_rs::SHIFT_JIS);
+
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode("[ar:Beyoncé]\n[00:01.00]Déjà vu");
+        let decoded = decode(&bytes);
+        assert_eq!(decoded.content, "[ar:Beyoncé]\n[00:01.00]Déjà vu");
+    }
+}
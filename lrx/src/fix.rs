@@ -0,0 +1,230 @@
+use super::{Color, LrxFile, Part};
+use anyhow::Result;
+
+/// Result of auto-fixing the text of an LRX file
+#[derive(Debug, Clone)]
+pub struct FixResult {
+    /// Normalized file content in canonical layout
+    pub content: String,
+    /// Human readable description of each fix applied
+    pub fixes: Vec<String>,
+    /// Comments and other non-tag lines that the rewrite drops
+    pub dropped_lines: usize,
+}
+
+impl FixResult {
+    pub fn changed(&self, original: &str) -> bool {
+        self.content != original
+    }
+}
+
+impl LrxFile {
+    /// Normalize the file in place: trim whitespace, move lines with negative timestamps
+    /// to the start, round timestamps to centiseconds, sort and de-duplicate lyric
+    /// lines, and define any parts that lines reference
+    /// but the file never declares. Returns a description of each fix applied.
+    pub fn normalize(&mut self) -> Vec<String> {
+        let mut fixes = Vec::new();
+
+        // Metadata whitespace
+        let mut trimmed = 0;
+        self.metadata = std::mem::take(&mut self.metadata)
+            .into_iter()
+            .map(|(key, value)| {
+                if key.trim() != key || value.trim() != value {
+                    trimmed += 1;
+                }
+                (key.trim().to_string(), value.trim().to_string())
+            })
+            .collect();
+
+        // Track and part names
+        for track in self.tracks.values_mut() {
+            if track.name.trim() != track.name {
+                track.name = track.name.trim().to_string();
+                trimmed += 1;
+            }
+        }
+        for part in self.parts.values_mut() {
+            if part.name.trim() != part.name {
+                part.name = part.name.trim().to_string();
+                trimmed += 1;
+            }
+        }
+
+        // Lyric text, part references and timestamps
+        let mut clamped = 0;
+        let mut rounded = 0;
+        for line in &mut self.lines {
+            if line.text.trim() != line.text {
+                line.text = line.text.trim().to_string();
+                trimmed += 1;
+            }
+            if let Some(part_id) = &mut line.part_id
+                && part_id.trim() != part_id
+            {
+                *part_id = part_id.trim().to_string();
+                trimmed += 1;
+            }
+
+            // The format can't write a negative time
+            if line.timestamp < 0.0 {
+                line.timestamp = 0.0;
+                clamped += 1;
+            }
+
+            let centiseconds = (line.timestamp * 100.0).round() / 100.0;
+            if centiseconds != line.timestamp {
+                line.timestamp = centiseconds;
+                rounded += 1;
+            }
+        }
+
+        if trimmed > 0 {
+            fixes.push(format!("Trimmed whitespace in {} place(s)", trimmed));
+        }
+        if clamped > 0 {
+            fixes.push(format!("Moved {} line(s) with a negative timestamp to 00:00.00", clamped));
+        }
+        if rounded > 0 {
+            fixes.push(format!("Rounded {} timestamp(s) to centiseconds", rounded));
+        }
+
+        // Sort
+        let is_sorted = self.lines.windows(2).all(|w| w[0].timestamp <= w[1].timestamp);
+        if !is_sorted {
+            self.finalize();
+            fixes.push("Sorted lyric lines by timestamp".to_string());
+        }
+
+        // Exact duplicates (same time, part and text)
+        let before = self.lines.len();
+        let mut seen = std::collections::HashSet::new();
+        self.lines.retain(|line| {
+            seen.insert((line.timestamp.to_bits(), line.part_id.clone(), line.text.clone()))
+        });
+        if self.lines.len() < before {
+            fixes.push(format!("Removed {} duplicate line(s)", before - self.lines.len()));
+        }
+
+        // Missing part definitions
+        let mut missing: Vec<String> = self.lines
+            .iter()
+            .filter_map(|line| line.part_id.clone())
+            .filter(|id| !self.parts.contains_key(id))
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        for id in missing {
+            let color = generated_color(self.parts.len());
+            fixes.push(format!("Defined missing part '{}' with color {}", id, color.to_hex()));
+            self.parts.insert(id.clone(), Part::with_color(id.clone(), id, color));
+        }
+
+        fixes
+    }
+}
+
+/// Parse, normalize and re-serialize LRX text in canonical layout
+pub fn fix_content(content: &str) -> Result<FixResult> {
+    let mut lrx = LrxFile::parse(content)?;
+    let mut fixes = lrx.normalize();
+    let fixed = lrx.to_string();
+
+    // Anything that survived the above but still differs is layout/formatting
+    if fixed != content && fixes.is_empty() {
+        fixes.push("Reformatted into canonical layout".to_string());
+    }
+
+    // A line the parser makes nothing of on its own (a comment, stray text, a
+    // bracketed tag it doesn't know) has nothing in the output to survive as
+    let dropped_lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| !LrxFile::parse(line).is_ok_and(|alone| !alone.to_string().is_empty()))
+        .count();
+
+    Ok(FixResult {
+        content: fixed,
+        fixes,
+        dropped_lines,
+    })
+}
+
+/// Distinct, readable colors for generated parts: walk the hue wheel by the golden angle
+fn generated_color(index: usize) -> Color {
+    let hue = (index as f64 * 137.508 + 330.0) % 360.0;
+    let (saturation, value) = (0.55, 1.0);
+
+    let c = value * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - c;
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let to_u8 = |v: f64| ((v + m) * 255.0).round() as u8;
+    Color::from_rgb(to_u8(r), to_u8(g), to_u8(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fix_content() {
+        let result = fix_content(
+            "[00:05.00][lead]Second  \n\
+             # a comment\n\
+             [ti: Title ]\n\
+             [00:01.00][lead]First\n\
+             [00:01.00][lead]First\n",
+        ).unwrap();
+
+        assert_eq!(result.dropped_lines, 1);
+        assert_eq!(result.fixes.len(), 3); // trim, duplicate, missing part
+        assert!(result.content.starts_with("[ti:Title]\n\n[part.lead:name=lead]\n"));
+        assert!(result.content.ends_with("[00:01.00][lead]First\n[00:05.00][lead]Second\n"));
+    }
+
+    #[test]
+    fn test_fix_content_counts_ignored_tags() {
+        let result = fix_content(
+            "[ti:Title]\n\
+             [chorus]\n\
+             [] stray\n\
+             [x] not a lyric\n\
+             [00:01.00]Kept\n",
+        ).unwrap();
+        assert_eq!(result.dropped_lines, 3);
+        assert_eq!(result.content, "[ti:Title]\n\n[00:01.00]Kept\n");
+    }
+
+    #[test]
+    fn test_fix_content_reports_negative_timestamps() {
+        let result = fix_content("[part.x:name=X]\n[00:-01.50][x]Early\n[00:02.00][x]Late\n").unwrap();
+        assert_eq!(result.fixes, vec!["Moved 1 line(s) with a negative timestamp to 00:00.00".to_string()]);
+        assert!(result.content.ends_with("[00:00.00][x]Early\n[00:02.00][x]Late\n"), "{}", result.content);
+    }
+
+    #[test]
+    fn test_fix_content_is_idempotent() {
+        let first = fix_content("[ar:A]\n[00:01.00][x]One\n").unwrap();
+        let second = fix_content(&first.content).unwrap();
+        assert!(second.fixes.is_empty());
+        assert!(!second.changed(&first.content));
+    }
+
+    #[test]
+    fn test_generated_colors_differ() {
+        assert_ne!(generated_color(0), generated_color(1));
+        assert_ne!(generated_color(1), generated_color(2));
+    }
+}
@@ -0,0 +1,237 @@
+//! LRX (Lyrics eXtended) file model, parser, serializer and validator.
+//!
+//! See `docs/lrx-format.md` in the Tanukioke repository for the format specification.
+//! Enable the `serde` feature to derive `Serialize`/`Deserialize` for all types, e.g.
+//! to dump an [`LrxFile`] to JSON.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod analyze;
+pub mod color;
+pub mod encoding;
+pub mod fix;
+pub mod parse;
+pub mod serialize;
+pub mod validate;
+
+pub use color::Color;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Track {
+    pub id: String,
+    pub name: String,
+    pub source: PathBuf,
+    pub volume: f32,
+    /// What the stem is (one of [`TRACK_ROLES`]), e.g. to route click tracks to the host's headphones
+    pub role: Option<String>,
+}
+
+/// Known values of a track's `role` property
+pub const TRACK_ROLES: &[&str] = &["instrumental", "vocal", "backing", "guide", "click"];
+
+impl Track {
+    pub fn new(id: String, name: String, source: PathBuf) -> Self {
+        Self {
+            id,
+            name,
+            source,
+            volume: 1.0,
+            role: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Part {
+    pub id: String,
+    pub name: String,
+    pub color: Color,
+}
+
+impl Part {
+    pub fn new(id: String, name: String) -> Self {
+        Self {
+            id,
+            name,
+            color: Color::WHITE,
+        }
+    }
+
+    pub fn with_color(id: String, name: String, color: Color) -> Self {
+        Self {
+            id,
+            name,
+            color,
+        }
+    }
+}
+
+/// A named stretch of the song (verse, chorus, bridge...), e.g. for looping it in
+/// practice. It runs until the next section starts.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Section {
+    pub id: String,
+    pub name: String,
+    pub start: f64, // seconds
+}
+
+impl Section {
+    pub fn new(id: String, name: String, start: f64) -> Self {
+        Self { id, name, start }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LyricLine {
+    pub timestamp: f64, // seconds
+    pub text: String,
+    pub part_id: Option<String>, // References a Part by id
+}
+
+impl LyricLine {
+    pub fn new(timestamp: f64, text: String) -> Self {
+        Self {
+            timestamp,
+            text,
+            part_id: None,
+        }
+    }
+
+    pub fn with_part(timestamp: f64, text: String, part_id: String) -> Self {
+        Self {
+            timestamp,
+            text,
+            part_id: Some(part_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LrxFile {
+    pub metadata: HashMap<String, String>,
+    pub tracks: HashMap<String, Track>,
+    pub parts: HashMap<String, Part>,
+    pub sections: HashMap<String, Section>,
+    pub lines: Vec<LyricLine>,
+    pub color: Option<Color>,
+    pub background_color: Option<Color>,
+}
+
+impl LrxFile {
+    pub fn new() -> Self {
+        Self {
+            metadata: HashMap::new(),
+            tracks: HashMap::new(),
+            parts: HashMap::new(),
+            sections: HashMap::new(),
+            lines: Vec::new(),
+            color: None,
+            background_color: None,
+        }
+    }
+
+    pub fn get_part(&self, part_id: &str) -> Option<&Part> {
+        self.parts.get(part_id)
+    }
+
+    pub fn get_track(&self, track_id: &str) -> Option<&Track> {
+        self.tracks.get(track_id)
+    }
+
+    /// Song length from the `length` tag, in seconds
+    pub fn length(&self) -> Option<f64> {
+        self.metadata_time("length")
+    }
+
+    /// Playback start trim point from the `start` tag, in seconds
+    pub fn start(&self) -> Option<f64> {
+        self.metadata_time("start")
+    }
+
+    /// Playback end trim point from the `end` tag, in seconds
+    pub fn end(&self) -> Option<f64> {
+        self.metadata_time("end")
+    }
+
+    /// Sections in the order they're played
+    pub fn sections_in_order(&self) -> Vec<&Section> {
+        let mut sections: Vec<&Section> = self.sections.values().collect();
+        sections.sort_by(|a, b| a.start.total_cmp(&b.start).then_with(|| a.id.cmp(&b.id)));
+        sections
+    }
+
+    /// Start and end of a section in seconds; the last section ends with the song (None)
+    pub fn section_range(&self, section_id: &str) -> Option<(f64, Option<f64>)> {
+        let section = self.sections.get(section_id)?;
+        let end = self.sections.values()
+            .map(|s| s.start)
+            .filter(|&start| start > section.start)
+            .min_by(f64::total_cmp);
+        Some((section.start, end))
+    }
+
+    /// Song tempo from the `bpm` tag, in beats per minute
+    pub fn bpm(&self) -> Option<f64> {
+        self.metadata.get("bpm")
+            .and_then(|s| s.trim().parse().ok())
+            .filter(|bpm: &f64| *bpm > 0.0)
+    }
+
+    /// Song loudness override from the `loudness` tag, in LUFS (used instead of the measured loudness)
+    pub fn loudness(&self) -> Option<f64> {
+        self.metadata.get("loudness").and_then(|s| s.trim().parse().ok())
+    }
+
+    fn metadata_time(&self, key: &str) -> Option<f64> {
+        self.metadata.get(key).and_then(|s| parse::parse_timestamp(s))
+    }
+
+    /// Post-process the LRX file: sort lyrics by timestamp
+    pub fn finalize(&mut self) {
+        // Sort lyrics by timestamp
+        self.lines.sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap_or(std::cmp::Ordering::Equal));
+    }
+}
+
+impl Default for LrxFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let lrx = LrxFile::parse(
+            "[ti:Song]\n\
+             [color:#DDDDDD]\n\
+             [track.inst:name=Instrumental]\n\
+             [track.inst:source=inst.flac]\n\
+             [part.lead:name=Lead]\n\
+             [part.lead:color=#FF6B9D]\n\
+             [00:01.00][lead]Hello\n",
+        ).unwrap();
+
+        let json = serde_json::to_string(&lrx).unwrap();
+        assert!(json.contains("\"#FF6B9D\""));
+
+        let loaded: LrxFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.metadata, lrx.metadata);
+        assert_eq!(loaded.color, lrx.color);
+        assert_eq!(loaded.tracks["inst"].source, lrx.tracks["inst"].source);
+        assert_eq!(loaded.parts["lead"].color, lrx.parts["lead"].color);
+        assert_eq!(loaded.lines[0].part_id, lrx.lines[0].part_id);
+    }
+}
@@ -1,6 +1,5 @@
-use super::{LrxFile, Track, Part, LyricLine};
+use super::{Color, LrxFile, Track, Part, Section, LyricLine};
 use std::path::PathBuf;
-use eframe::egui::Color32;
 use anyhow::{anyhow, Context, Result};
 
 impl LrxFile {
@@ -88,7 +87,7 @@ fn extract_brackets(line: &str) -> Result<Vec<String>> {
     Ok(segments)
 }
 
-fn parse_timestamp(s: &str) -> Option<f64> {
+pub(crate) fn parse_timestamp(s: &str) -> Option<f64> {
     // Format: mm:ss.xx or mm:ss
     let parts: Vec<&str> = s.split(':').collect();
     if parts.len() != 2 {
@@ -159,11 +158,11 @@ fn parse_tag(lrx: &mut LrxFile, tag: &str) -> Result<()> {
         parse_dot_notation(lrx, key, value)?;
     } else if key == "color" {
         // Global foreground color
-        lrx.color = Some(parse_color(value)?);
+        lrx.color = Some(Color::from_hex(value)?);
         lrx.metadata.insert(key.to_string(), value.to_string());
     } else if key == "background_color" {
         // Global background color
-        lrx.background_color = Some(parse_color(value)?);
+        lrx.background_color = Some(Color::from_hex(value)?);
         lrx.metadata.insert(key.to_string(), value.to_string());
     } else {
         // Simple metadata tag
@@ -179,7 +178,7 @@ fn parse_dot_notation(lrx: &mut LrxFile, key: &str, value: &str) -> Result<()> {
         return Err(anyhow!("Invalid dot notation: {}", key));
     }
 
-    let category = parts[0]; // "track" or "part"
+    let category = parts[0]; // "track", "part" or "section"
     let id = parts[1]; // just the id
 
     // Value should be "property=value"
@@ -194,6 +193,7 @@ fn parse_dot_notation(lrx: &mut LrxFile, key: &str, value: &str) -> Result<()> {
     match category {
         "track" => parse_track_property(lrx, id, property, actual_value)?,
         "part" => parse_part_property(lrx, id, property, actual_value)?,
+        "section" => parse_section_property(lrx, id, property, actual_value)?,
         _ => return Err(anyhow!("Unknown category: {}", category)),
     }
 
@@ -206,10 +206,12 @@ fn parse_track_property(lrx: &mut LrxFile, id: &str, property: &str, value: &str
         name: String::new(),
         source: PathBuf::new(),
         volume: 1.0,
+        role: None,
     });
 
     match property {
         "name" => track.name = value.to_string(),
+        "role" => track.role = Some(value.to_string()),
         "source" => track.source = PathBuf::from(value),
         "volume" => track.volume = value.parse()
             .with_context(|| format!("Invalid volume value: {}", value))?,
@@ -223,32 +225,30 @@ fn parse_part_property(lrx: &mut LrxFile, id: &str, property: &str, value: &str)
     let part = lrx.parts.entry(id.to_string()).or_insert_with(|| Part {
         id: id.to_string(),
         name: String::new(),
-        color: Color32::WHITE,
+        color: Color::WHITE,
     });
 
     match property {
         "name" => part.name = value.to_string(),
-        "color" => part.color = parse_color(value)?,
+        "color" => part.color = Color::from_hex(value)?,
         _ => return Err(anyhow!("Unknown part property: {}", property)),
     }
 
     Ok(())
 }
 
-fn parse_color(s: &str) -> Result<Color32> {
-    // Expected format: #RRGGBB
-    if !s.starts_with('#') || s.len() != 7 {
-        return Err(anyhow!("Invalid color format: {}. Expected #RRGGBB", s));
-    }
+fn parse_section_property(lrx: &mut LrxFile, id: &str, property: &str, value: &str) -> Result<()> {
+    let section = lrx.sections.entry(id.to_string())
+        .or_insert_with(|| Section::new(id.to_string(), String::new(), 0.0));
 
-    let r = u8::from_str_radix(&s[1..3], 16)
-        .with_context(|| format!("Invalid red component in color: {}", s))?;
-    let g = u8::from_str_radix(&s[3..5], 16)
-        .with_context(|| format!("Invalid green component in color: {}", s))?;
-    let b = u8::from_str_radix(&s[5..7], 16)
-        .with_context(|| format!("Invalid blue component in color: {}", s))?;
+    match property {
+        "name" => section.name = value.to_string(),
+        "start" => section.start = parse_timestamp(value)
+            .ok_or_else(|| anyhow!("Invalid section start time: {}", value))?,
+        _ => return Err(anyhow!("Unknown section property: {}", property)),
+    }
 
-    Ok(Color32::from_rgb(r, g, b))
+    Ok(())
 }
 
 #[cfg(test)]
@@ -263,18 +263,6 @@ mod tests {
         assert_eq!(parse_timestamp("invalid"), None);
     }
 
-    #[test]
-    fn test_parse_color() {
-        let white = parse_color("#FFFFFF").unwrap();
-        assert_eq!(white, Color32::from_rgb(255, 255, 255));
-
-        let red = parse_color("#FF0000").unwrap();
-        assert_eq!(red, Color32::from_rgb(255, 0, 0));
-
-        assert!(parse_color("FFFFFF").is_err());
-        assert!(parse_color("#FFF").is_err());
-    }
-
     #[test]
     fn test_extract_brackets() {
         let result = extract_brackets("[00:12.00][lead]Text here").unwrap();
@@ -283,4 +271,20 @@ mod tests {
         let result = extract_brackets("[ar:Artist]").unwrap();
         assert_eq!(result, vec!["ar:Artist"]);
     }
+
+    #[test]
+    fn test_parse_sections() {
+        let lrx = LrxFile::parse(
+            "[section.verse1:name=Verse 1]\n\
+             [section.verse1:start=00:12.00]\n\
+             [section.chorus:name=Chorus]\n\
+             [section.chorus:start=00:45.50]\n",
+        ).unwrap();
+        let names: Vec<&str> = lrx.sections_in_order().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Verse 1", "Chorus"]);
+        assert_eq!(lrx.section_range("verse1"), Some((12.0, Some(45.5))));
+        assert_eq!(lrx.section_range("chorus"), Some((45.5, None)));
+
+        assert!(LrxFile::parse("[section.verse1:start=soon]\n").is_err());
+    }
 }
@@ -0,0 +1,194 @@
+use super::{LrxFile, Track, Part, Section, LyricLine};
+
+/// Metadata tags in the order they are written; other tags follow alphabetically
+const METADATA_ORDER: &[&str] = &[
+    "ar", "ti", "al", "length", "start", "end", "key", "bpm", "au", "lr", "by", "offset", "loudness", "color", "background_color",
+];
+
+impl LrxFile {
+    /// Serialize an LRX file to a string
+    ///
+    /// Sections are written in canonical order (metadata, tracks, parts, song sections,
+    /// lyrics), tracks/parts are sorted by id and song sections by start, so the output
+    /// is stable across saves.
+    pub fn to_string(&self) -> String {
+        let mut output = String::new();
+
+        // Write metadata tags
+        for key in sorted_metadata_keys(self) {
+            output.push_str(&format!("[{}:{}]\n", key, self.metadata[key]));
+        }
+
+        if !self.metadata.is_empty() {
+            output.push('\n');
+        }
+
+        // Write track definitions
+        let mut track_ids: Vec<&String> = self.tracks.keys().collect();
+        track_ids.sort();
+        for (i, track_id) in track_ids.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+            output.push_str(&serialize_track(track_id, &self.tracks[*track_id]));
+        }
+
+        if !self.tracks.is_empty() {
+            output.push('\n');
+        }
+
+        // Write part definitions
+        let mut part_ids: Vec<&String> = self.parts.keys().collect();
+        part_ids.sort();
+        for (i, part_id) in part_ids.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+            output.push_str(&serialize_part(part_id, &self.parts[*part_id]));
+        }
+
+        if !self.parts.is_empty() {
+            output.push('\n');
+        }
+
+        // Write song section definitions
+        for (i, section) in self.sections_in_order().into_iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+            output.push_str(&serialize_section(section));
+        }
+
+        if !self.sections.is_empty() {
+            output.push('\n');
+        }
+
+        // Write lyric lines
+        for line in &self.lines {
+            output.push_str(&serialize_lyric_line(line));
+        }
+
+        output
+    }
+}
+
+fn sorted_metadata_keys(lrx: &LrxFile) -> Vec<&String> {
+    let mut keys: Vec<&String> = lrx.metadata.keys().collect();
+    keys.sort_by_key(|key| {
+        let rank = METADATA_ORDER.iter()
+            .position(|k| k == key)
+            .unwrap_or(METADATA_ORDER.len());
+        (rank, key.as_str())
+    });
+    keys
+}
+
+fn serialize_track(id: &str, track: &Track) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("[track.{}:name={}]\n", id, track.name));
+    output.push_str(&format!("[track.{}:source={}]\n", id, track.source.display()));
+    output.push_str(&format!("[track.{}:volume={:?}]\n", id, track.volume));
+    if let Some(role) = &track.role {
+        output.push_str(&format!("[track.{}:role={}]\n", id, role));
+    }
+
+    output
+}
+
+fn serialize_part(id: &str, part: &Part) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("[part.{}:name={}]\n", id, part.name));
+    output.push_str(&format!("[part.{}:color={}]\n", id, part.color.to_hex()));
+
+    output
+}
+
+fn serialize_section(section: &Section) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("[section.{}:name={}]\n", section.id, section.name));
+    output.push_str(&format!("[section.{}:start={}]\n", section.id, format_timestamp(section.start)));
+
+    output
+}
+
+fn serialize_lyric_line(line: &LyricLine) -> String {
+    let timestamp = format_timestamp(line.timestamp);
+
+    if let Some(part_id) = &line.part_id {
+        format!("[{}][{}]{}\n", timestamp, part_id, line.text)
+    } else {
+        format!("[{}]{}\n", timestamp, line.text)
+    }
+}
+
+pub(crate) fn format_timestamp(seconds: f64) -> String {
+    // Round to whole centiseconds first so 59.999 becomes 01:00.00, not 00:60.00
+    let centiseconds = (seconds.max(0.0) * 100.0).round() as u64;
+    let minutes = centiseconds / 6000;
+    let secs = (centiseconds % 6000) / 100;
+    let hundredths = centiseconds % 100;
+
+    // Format as mm:ss.xx
+    format!("{:02}:{:02}.{:02}", minutes, secs, hundredths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(12.0), "00:12.00");
+        assert_eq!(format_timestamp(90.5), "01:30.50");
+        assert_eq!(format_timestamp(135.0), "02:15.00");
+        assert_eq!(format_timestamp(59.999), "01:00.00");
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let lrx = LrxFile::parse(
+            "[00:01.00][b]Two\n\
+             [part.b:name=B]\n\
+             [zz:Custom]\n\
+             [part.a:name=A]\n\
+             [ti:Title]\n\
+             [ar:Artist]\n",
+        ).unwrap();
+
+        assert_eq!(
+            lrx.to_string(),
+            "[ar:Artist]\n[ti:Title]\n[zz:Custom]\n\n\
+             [part.a:name=A]\n[part.a:color=#FFFFFF]\n\n\
+             [part.b:name=B]\n[part.b:color=#FFFFFF]\n\n\
+             [00:01.00][b]Two\n"
+        );
+    }
+
+    #[test]
+    fn test_sections_sorted_by_start() {
+        let lrx = LrxFile::parse(
+            "[section.chorus:name=Chorus]\n\
+             [section.chorus:start=00:45.50]\n\
+             [section.verse:name=Verse]\n\
+             [section.verse:start=00:12.00]\n",
+        ).unwrap();
+
+        assert_eq!(
+            lrx.to_string(),
+            "[section.verse:name=Verse]\n[section.verse:start=00:12.00]\n\n\
+             [section.chorus:name=Chorus]\n[section.chorus:start=00:45.50]\n\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_lyric_line() {
+        let line = LyricLine::new(12.0, "Test lyrics".to_string());
+        assert_eq!(serialize_lyric_line(&line), "[00:12.00]Test lyrics\n");
+
+        let line_with_part = LyricLine::with_part(12.0, "Test lyrics".to_string(), "lead".to_string());
+        assert_eq!(serialize_lyric_line(&line_with_part), "[00:12.00][lead]Test lyrics\n");
+    }
+}
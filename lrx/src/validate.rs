@@ -0,0 +1,215 @@
+use super::LrxFile;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+    pub line_index: Option<usize>, // Index into LrxFile::lines, if the issue is about a lyric line
+}
+
+impl ValidationIssue {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            line_index: None,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            line_index: None,
+        }
+    }
+}
+
+impl LrxFile {
+    /// Check the file for structural problems the parser accepts but the player can't use
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        // Trim points
+        for key in ["length", "start", "end"] {
+            if let Some(value) = self.metadata.get(key)
+                && super::parse::parse_timestamp(value).is_none()
+            {
+                issues.push(ValidationIssue::error(format!("Invalid {} time '{}', expected mm:ss.xx", key, value)));
+            }
+        }
+        if let Some(value) = self.metadata.get("loudness")
+            && self.loudness().is_none()
+        {
+            issues.push(ValidationIssue::error(format!("Invalid loudness '{}', expected LUFS like -14.0", value)));
+        }
+        if let Some(value) = self.metadata.get("bpm")
+            && self.bpm().is_none()
+        {
+            issues.push(ValidationIssue::error(format!("Invalid bpm '{}', expected a tempo like 120", value)));
+        }
+        if let (Some(start), Some(end)) = (self.start(), self.end())
+            && start >= end
+        {
+            issues.push(ValidationIssue::error(format!("Start point {:.2}s is not before end point {:.2}s", start, end)));
+        }
+
+        // Tracks, in id order so the report is stable
+        let mut track_ids: Vec<&String> = self.tracks.keys().collect();
+        track_ids.sort();
+        for id in track_ids {
+            let track = &self.tracks[id];
+            if track.name.is_empty() {
+                issues.push(ValidationIssue::warning(format!("Track '{}' has no name", id)));
+            }
+            if track.source.as_os_str().is_empty() {
+                issues.push(ValidationIssue::error(format!("Track '{}' has no source", id)));
+            }
+            if !(0.0..=1.0).contains(&track.volume) {
+                issues.push(ValidationIssue::warning(format!(
+                    "Track '{}' volume {} is outside 0.0 to 1.0", id, track.volume
+                )));
+            }
+            if let Some(role) = &track.role
+                && !crate::TRACK_ROLES.contains(&role.as_str())
+            {
+                issues.push(ValidationIssue::warning(format!(
+                    "Track '{}' has unknown role '{}' (expected one of: {})", id, role, crate::TRACK_ROLES.join(", ")
+                )));
+            }
+        }
+
+        // Parts
+        let mut part_ids: Vec<&String> = self.parts.keys().collect();
+        part_ids.sort();
+        for id in part_ids {
+            if self.parts[id].name.is_empty() {
+                issues.push(ValidationIssue::warning(format!("Part '{}' has no name", id)));
+            }
+        }
+
+        // Song sections
+        for section in self.sections_in_order() {
+            if section.name.is_empty() {
+                issues.push(ValidationIssue::warning(format!("Section '{}' has no name", section.id)));
+            }
+        }
+
+        // Lyric lines
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.timestamp < 0.0 {
+                issues.push(ValidationIssue {
+                    line_index: Some(i),
+                    ..ValidationIssue::error(format!("Line {} has a negative timestamp", i + 1))
+                });
+            }
+
+            if let Some(part_id) = &line.part_id
+                && !self.parts.contains_key(part_id)
+            {
+                issues.push(ValidationIssue {
+                    line_index: Some(i),
+                    ..ValidationIssue::warning(format!(
+                        "Line {} references undefined part '{}'", i + 1, part_id
+                    ))
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_valid_file() {
+        let lrx = LrxFile::parse(
+            "[track.inst:name=Instrumental]\n\
+             [track.inst:source=inst.flac]\n\
+             [part.lead:name=Lead]\n\
+             [00:01.00][lead]Hello\n",
+        ).unwrap();
+        assert!(lrx.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_problems() {
+        let lrx = LrxFile::parse(
+            "[track.inst:name=Instrumental]\n\
+             [track.inst:volume=1.5]\n\
+             [00:01.00][lead]Hello\n",
+        ).unwrap();
+        let issues = lrx.validate();
+
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].severity, Severity::Error); // no source
+        assert_eq!(issues[1].severity, Severity::Warning); // volume
+        assert_eq!(issues[2].line_index, Some(0)); // undefined part
+    }
+
+    #[test]
+    fn test_validate_trim_points() {
+        let lrx = LrxFile::parse("[start:00:10.00]\n[end:00:05.00]\n").unwrap();
+        assert_eq!(lrx.start(), Some(10.0));
+        assert_eq!(lrx.end(), Some(5.0));
+        assert_eq!(lrx.validate().len(), 1);
+
+        let lrx = LrxFile::parse("[start:soon]\n").unwrap();
+        assert_eq!(lrx.start(), None);
+        assert_eq!(lrx.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_loudness() {
+        let lrx = LrxFile::parse("[loudness:-12.5]\n").unwrap();
+        assert_eq!(lrx.loudness(), Some(-12.5));
+        assert!(lrx.validate().is_empty());
+
+        let lrx = LrxFile::parse("[loudness:loud]\n").unwrap();
+        assert_eq!(lrx.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_sections_and_bpm() {
+        let lrx = LrxFile::parse("[bpm:96]\n[section.intro:start=00:00.00]\n").unwrap();
+        assert_eq!(lrx.bpm(), Some(96.0));
+        let issues = lrx.validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning); // unnamed section
+
+        let lrx = LrxFile::parse("[bpm:fast]\n").unwrap();
+        assert_eq!(lrx.validate()[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_validate_track_role() {
+        let lrx = LrxFile::parse(
+            "[track.click:name=Click]\n\
+             [track.click:source=click.wav]\n\
+             [track.click:role=click]\n",
+        ).unwrap();
+        assert_eq!(lrx.tracks["click"].role.as_deref(), Some("click"));
+        assert!(lrx.validate().is_empty());
+
+        let lrx = LrxFile::parse(
+            "[track.click:name=Click]\n\
+             [track.click:source=click.wav]\n\
+             [track.click:role=metronome]\n",
+        ).unwrap();
+        assert_eq!(lrx.validate()[0].severity, Severity::Warning);
+    }
+}
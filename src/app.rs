@@ -1,12 +1,16 @@
 use eframe::egui;
 use std::sync::{Arc, Mutex};
 
+/// How often to try the sound device again after it couldn't be reopened
+const DEVICE_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Shared playback state that can be accessed from multiple windows
 #[derive(Debug, Clone)]
 pub struct PlaybackState {
     pub position: f64,      // Current position in seconds
     pub duration: f64,      // Total duration in seconds
+    pub start: f64,         // Start of the playable window in seconds ([start:] tag)
+    pub end: f64,           // End of the playable window in seconds ([end:] tag, or duration)
     pub is_playing: bool,   // Whether audio is playing
     pub is_paused: bool,    // Whether audio is paused
     pub lyrics_snappiness: f32, // Easing strength for lyrics scrolling (0=linear, higher=more snap)
@@ -17,6 +21,8 @@ impl Default for PlaybackState {
         Self {
             position: 0.0,
             duration: 0.0,
+            start: 0.0,
+            end: 0.0,
             is_playing: false,
             is_paused: false,
             lyrics_snappiness: 10.0,
@@ -36,12 +42,14 @@ impl PlaybackState {
 pub struct App {
     pub playback_state: Arc<Mutex<PlaybackState>>,
     pub audio_engine: Arc<Mutex<crate::audio::AudioEngine>>,
+    engine_events: std::sync::mpsc::Receiver<crate::audio::events::EngineEvent>,
     show_lyrics_window: bool,
     lyrics_window: Option<crate::ui::lyrics_window::LyricsWindow>,
     config: crate::config::Config,
     config_dirty: bool,
     last_config_save: std::time::Instant,
     current_song_metadata: std::collections::HashMap<String, String>,
+    current_song_path: Option<std::path::PathBuf>,
     library_songs: Vec<crate::library::Song>,
     library_search_query: String,
     show_rescan_confirm: bool,
@@ -55,14 +63,53 @@ pub struct App {
     show_editor_window: bool,
     editor_state: crate::ui::lrx_editor::EditorState,
     show_settings_window: bool,
+    resave_utf8_dialog: Option<crate::ui::encoding_dialog::ResaveUtf8Dialog>,
+    mixdown: Option<MixdownJob>,
+    loader: crate::loader::SongLoader,
+    play_when_loaded: Option<std::path::PathBuf>, // Start this song as soon as it's loaded
+    interstitial: Option<Interstitial>,
+    practice: crate::ui::practice::PracticeState, // Loop points picked in the practice controls
+    audio_output: crate::audio::output::OutputBackend, // From the config or the command line
+    audio_devices: Option<Vec<String>>, // Listed when the settings window needs them
+    device_failure: Option<(String, std::time::Instant)>, // Why the device couldn't be reopened, and when to retry
+}
+
+/// An offline render of the current song running on a background thread
+struct MixdownJob {
+    output: std::path::PathBuf,
+    progress: Arc<std::sync::atomic::AtomicU32>, // f32 bits, 0.0..=1.0
+    handle: std::thread::JoinHandle<anyhow::Result<std::time::Duration>>,
+}
+
+/// The "up next" screen between songs in auto-advance mode, counting down to
+/// the next queue entry
+struct Interstitial {
+    entry_id: usize,
+    ends_at: Option<std::time::Instant>, // None while the host holds it
+    remaining: std::time::Duration,      // Time left when held
+}
+
+impl Interstitial {
+    fn remaining(&self) -> Option<std::time::Duration> {
+        self.ends_at.map(|ends_at| ends_at.saturating_duration_since(std::time::Instant::now()))
+    }
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(audio_output: Option<crate::audio::output::OutputBackend>) -> Self {
         let config = crate::config::Config::load().unwrap_or_default();
 
-        let audio_engine = crate::audio::AudioEngine::new()
-            .expect("Failed to initialize audio engine");
+        // Keep running without sound rather than failing when there's no usable output
+        let audio_output = audio_output.unwrap_or_else(|| config.audio_output.clone());
+        let mut audio_engine = crate::audio::AudioEngine::new(&audio_output, config.audio_device.as_deref(), config.virtual_channels).unwrap_or_else(|e| {
+            eprintln!("Failed to open audio output '{}': {:#}", audio_output, e);
+            eprintln!("Continuing without sound");
+            crate::audio::AudioEngine::with_output(Box::new(crate::audio::output::VirtualOutput::null(2)))
+        });
+        audio_engine.set_master_volume(config.master_volume);
+        audio_engine.set_transport_fade(std::time::Duration::from_millis(config.transport_fade_ms));
+        audio_engine.set_output_latency(config.output_latency_ms.map(std::time::Duration::from_millis));
+        let engine_events = audio_engine.subscribe();
 
         let playback_state = Arc::new(Mutex::new(PlaybackState::new(config.lyrics_snappiness)));
         let audio_engine = Arc::new(Mutex::new(audio_engine));
@@ -92,12 +139,14 @@ impl App {
         Self {
             playback_state,
             audio_engine,
+            engine_events,
             show_lyrics_window: true,
             lyrics_window,
             config,
             config_dirty: false,
             last_config_save: std::time::Instant::now(),
             current_song_metadata: std::collections::HashMap::new(),
+            current_song_path: None,
             library_songs,
             library_search_query: String::new(),
             show_rescan_confirm: false,
@@ -111,55 +160,115 @@ impl App {
             show_editor_window: false,
             editor_state: crate::ui::lrx_editor::EditorState::new(),
             show_settings_window: false,
+            resave_utf8_dialog: None,
+            mixdown: None,
+            loader: crate::loader::SongLoader::default(),
+            play_when_loaded: None,
+            interstitial: None,
+            practice: Default::default(),
+            audio_output,
+            audio_devices: None,
+            device_failure: None,
         }
     }
 
-    /// Load a song from an LRX file and its associated audio tracks
-    pub fn load_song(&mut self, lrx_path: std::path::PathBuf) -> anyhow::Result<()> {
-        use anyhow::Context;
-
-        // Read and parse LRX file
-        let content = std::fs::read_to_string(&lrx_path)
-            .with_context(|| format!("Failed to read LRX file: {}", lrx_path.display()))?;
-
-        let lrx = crate::lrx::LrxFile::parse(&content)
-            .with_context(|| format!("Failed to parse LRX file: {}", lrx_path.display()))?;
-
-        // Get the directory containing the LRX file (for resolving relative audio paths)
-        let song_dir = lrx_path.parent()
-            .ok_or_else(|| anyhow::anyhow!("LRX file has no parent directory"))?
-            .to_path_buf();
-
-        // Prepare track info for audio engine
-        let track_infos: Vec<(String, String, std::path::PathBuf, f32)> = lrx.tracks
-            .values()
-            .map(|track| {
-                (
-                    track.id.clone(),
-                    track.name.clone(),
-                    track.source.clone(),
-                    track.volume,
-                )
-            })
-            .collect();
+    /// Start loading a song from an LRX file and its associated audio tracks. The
+    /// files are read on a background thread and the song replaces the current
+    /// one when they're ready (see `poll_loading`).
+    pub fn load_song(&mut self, lrx_path: std::path::PathBuf) {
+        // Picking a song by hand takes over from auto-advance
+        self.play_when_loaded = None;
+        self.interstitial = None;
+
+        let lookup = self.song_lookup(&lrx_path);
+        self.loader.load(lrx_path, lookup);
+    }
+
+    /// What the loader needs to know about a song from the settings and the library cache
+    fn song_lookup(&self, lrx_path: &std::path::Path) -> crate::loader::SongLookup {
+        let song = self.library_songs.iter().find(|s| s.lrx_path.as_deref() == Some(lrx_path));
+        crate::loader::SongLookup {
+            normalize: self.config.loudness_normalization,
+            cached_loudness: song.and_then(|s| s.cached_loudness()),
+            cached_durations: song.map(|s| s.stem_durations.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Switch to a song whose files have been read and opened
+    fn apply_song(&mut self, song: crate::loader::PreparedSong) {
+        let crate::loader::PreparedSong {
+            lrx_path, song_dir, decoded, lrx, tracks, loudness, measured_loudness, scanned_durations,
+        } = song;
+
+        // Offer to convert legacy-encoded files to UTF-8
+        if !decoded.is_utf8() {
+            self.resave_utf8_dialog = Some(crate::ui::encoding_dialog::ResaveUtf8Dialog {
+                path: lrx_path.clone(),
+                encoding_name: decoded.encoding_name(),
+                content: decoded.content,
+            });
+        }
+
+        let measured = loudness.zip(measured_loudness);
+        if measured.is_some() || !scanned_durations.is_empty() {
+            self.cache_in_registry(&lrx_path, |song| {
+                if let Some((loudness, key)) = measured {
+                    song.loudness = Some(loudness);
+                    song.loudness_key = Some(key);
+                }
+                for scanned in scanned_durations {
+                    song.stem_durations.retain(|cached| cached.path != scanned.path);
+                    song.stem_durations.push(scanned);
+                }
+            });
+        }
 
-        // Load tracks into audio engine
+        // Hand the opened tracks to the audio engine
         let mut engine = self.audio_engine.lock().unwrap();
-        engine.set_base_dir(song_dir.clone());
-        engine.load_tracks(track_infos)
-            .context("Failed to load audio tracks")?;
+        engine.set_base_dir(song_dir);
+        engine.load_opened(tracks);
+
+        // Click and guide tracks go to the monitor outputs when there are any
+        for track in engine.tracks_mut() {
+            let role = lrx.tracks.get(&track.id).and_then(|t| t.role.as_deref());
+            track.set_output_pair(crate::audio::default_output_pair(role));
+        }
+
+        // Restore this song's effects settings
+        if let Some(settings) = self.config.songs.get(lrx_path.to_string_lossy().as_ref()) {
+            for track in engine.tracks_mut() {
+                if let Some(dsp) = settings.track_dsp.get(&track.id) {
+                    track.set_dsp(dsp.clone());
+                }
+            }
+        }
+
+        if let Some(loudness) = loudness {
+            let gain = crate::audio::loudness::normalization_gain_db(loudness, self.config.loudness_target_lufs);
+            engine.set_loudness_gain_db(gain as f32);
+            println!("Loudness {:.1} LUFS, normalization gain {:+.1} dB", loudness, gain);
+        }
+
+        // Apply start/end trim points
+        let trim_start = std::time::Duration::from_secs_f64(lrx.start().unwrap_or(0.0).max(0.0));
+        let trim_end = lrx.end().map(|end| std::time::Duration::from_secs_f64(end.max(0.0)));
+        engine.set_trim(trim_start, trim_end);
 
         // Update playback state duration
         let duration = engine.duration();
+        let (start, end) = (engine.trim_start(), engine.trim_end());
         drop(engine);
 
         let mut state = self.playback_state.lock().unwrap();
         state.duration = duration.as_secs_f64();
-        state.position = 0.0;
+        state.start = start.as_secs_f64();
+        state.end = end.as_secs_f64();
+        state.position = start.as_secs_f64();
         drop(state);
 
         // Store metadata for display
         self.current_song_metadata = lrx.metadata.clone();
+        self.current_song_path = Some(lrx_path.clone());
 
         // Update lyrics window if it exists
         self.lyrics_window = Some(
@@ -169,9 +278,58 @@ impl App {
                 self.config.clone()
             )
         );
+        self.practice.clear();
+        self.apply_count_in();
 
         println!("Loaded song from: {}", lrx_path.display());
-        Ok(())
+    }
+
+    /// Set the practice loop count-in from the settings, at the current song's tempo
+    fn apply_count_in(&self) {
+        let bpm = self.lyrics_window.as_ref()
+            .and_then(|window| window.lyrics())
+            .and_then(|lyrics| lyrics.bpm())
+            .unwrap_or(self.config.count_in_bpm);
+        let count_in = self.config.count_in
+            .then(|| crate::audio::looping::CountIn::at_tempo(self.config.count_in_beats, bpm));
+        self.audio_engine.lock().unwrap().set_count_in(count_in);
+    }
+
+    /// Remember a song's measured loudness or scanned length in the library
+    /// registry, so each is only worked out once
+    fn cache_in_registry(&mut self, lrx_path: &std::path::Path, update: impl FnOnce(&mut crate::library::Song)) {
+        let Some(song) = self.library_songs.iter_mut().find(|s| s.lrx_path.as_deref() == Some(lrx_path)) else {
+            return;
+        };
+        update(song);
+        if let Some(library_path) = &self.config.library_path {
+            let registry_path = std::path::PathBuf::from(library_path).join("library.toml");
+            if let Err(e) = crate::library::save_registry(&registry_path, &self.library_songs) {
+                eprintln!("Warning: Failed to save library registry: {}", e);
+            }
+        }
+    }
+
+    /// Switch to a song once the loader has it ready, and keep the next queue
+    /// entry preloaded
+    fn poll_loading(&mut self) {
+        if let Some((lrx_path, result)) = self.loader.poll() {
+            let play = self.play_when_loaded.take().is_some_and(|path| path == lrx_path);
+            match result {
+                Ok(song) => {
+                    self.apply_song(song);
+                    if play {
+                        self.audio_engine.lock().unwrap().play();
+                    }
+                }
+                Err(e) => eprintln!("Failed to load song {}: {:#}", lrx_path.display(), e),
+            }
+        }
+
+        if let Some(lrx_path) = self.queue.upcoming().and_then(|entry| entry.lrx_path.clone()) {
+            let lookup = self.song_lookup(&lrx_path);
+            self.loader.preload(lrx_path, lookup);
+        }
     }
 
 }
@@ -182,15 +340,26 @@ impl eframe::App for App {
         self.save_config_if_needed(false);
 
         // Update playback state from audio engine
+        let intermission = self.intermission();
         {
             let mut engine = self.audio_engine.lock().unwrap();
+            engine.set_intermission(intermission);
             let mut state = self.playback_state.lock().unwrap();
             engine.update_playback_state(&mut state);
         }
+        while let Ok(event) = self.engine_events.try_recv() {
+            self.handle_engine_event(event);
+        }
+        if self.device_failure.as_ref().is_some_and(|(_, retry_at)| std::time::Instant::now() >= *retry_at) {
+            self.switch_audio_device();
+        }
+        self.tick_interstitial();
 
         // Show lyrics window as a separate viewport if requested
         if self.show_lyrics_window {
+            let up_next = self.up_next();
             if let Some(mut lyrics_window) = self.lyrics_window.take() {
+                lyrics_window.set_up_next(up_next);
                 ctx.show_viewport_immediate(
                     egui::ViewportId::from_hash_of("lyrics_window"),
                     egui::ViewportBuilder::default()
@@ -224,23 +393,27 @@ impl eframe::App for App {
                     .with_inner_size([800.0, 600.0]),
                 |ctx, _class| {
                     egui::CentralPanel::default().show(ctx, |ui| {
-                        let playback_position = {
+                        let (playback_position, song_duration) = {
                             let state = self.playback_state.lock().unwrap();
-                            if state.is_playing || state.is_paused {
-                                Some(state.position)
-                            } else {
-                                None
-                            }
+                            let position = (state.is_playing || state.is_paused).then_some(state.position);
+                            (position, (state.duration > 0.0).then_some(state.duration))
                         };
+                        // Lines can be checked against the audio when it's the loaded song
+                        let editing_loaded_song = self.editor_state.file_path.is_some()
+                            && self.editor_state.file_path == self.current_song_path;
+                        self.editor_state.set_song_duration(song_duration.filter(|_| editing_loaded_song));
 
                         if let Some(action) = crate::ui::lrx_editor::render(ui, &mut self.editor_state, playback_position) {
                             match action {
                                 crate::ui::lrx_editor::EditorAction::Save(path, content) => {
                                     match std::fs::write(&path, content) {
                                         Ok(_) => {
+                                            // A preloaded copy of this song is out of date now
+                                            self.loader.forget_preload(&path);
+
                                             // Reload to update original_content
-                                            if let Ok(new_content) = std::fs::read_to_string(&path) {
-                                                self.editor_state.load(path, new_content);
+                                            if let Ok(decoded) = crate::lrx::encoding::read_to_string(&path) {
+                                                self.editor_state.load(path, decoded);
                                             }
                                         }
                                         Err(e) => {
@@ -280,14 +453,27 @@ impl eframe::App for App {
                     .with_inner_size([500.0, 600.0]),
                 |ctx, _class| {
                     egui::CentralPanel::default().show(ctx, |ui| {
+                        let audio_device = self.config.audio_device.clone();
                         let config_changed = crate::ui::settings::render(
                             ui,
                             &mut self.config,
                             &self.playback_state,
+                            &mut self.audio_devices,
                         );
+                        if self.config.audio_device != audio_device {
+                            self.switch_audio_device();
+                        }
 
                         if config_changed {
                             self.mark_config_dirty();
+                            let transport_fade = std::time::Duration::from_millis(self.config.transport_fade_ms);
+                            let output_latency = self.config.output_latency_ms.map(std::time::Duration::from_millis);
+                            {
+                                let mut engine = self.audio_engine.lock().unwrap();
+                                engine.set_transport_fade(transport_fade);
+                                engine.set_output_latency(output_latency);
+                            }
+                            self.apply_count_in();
                             // Update lyrics window with new config
                             if let Some(ref mut lyrics_window) = self.lyrics_window {
                                 lyrics_window.update_config(self.config.clone());
@@ -312,8 +498,69 @@ impl eframe::App for App {
                             // Toggle settings window visibility
                             self.show_settings_window = !self.show_settings_window;
                         }
+                        crate::ui::player::PlayerAction::MasterVolumeChanged(volume) => {
+                            self.config.master_volume = volume;
+                            self.mark_config_dirty();
+                        }
+                        crate::ui::player::PlayerAction::Mixdown => self.start_mixdown(),
+                        crate::ui::player::PlayerAction::FadeOut => {
+                            let over = std::time::Duration::from_secs_f32(self.config.fade_out_seconds.max(0.0));
+                            self.audio_engine.lock().unwrap().fade_out(over);
+                        }
+                        crate::ui::player::PlayerAction::TrackDspChanged(track_id, dsp) => {
+                            if let Some(path) = &self.current_song_path {
+                                let key = path.to_string_lossy().to_string();
+                                let song = self.config.songs.entry(key.clone()).or_default();
+                                // Settings of switched-off effects (e.g. a compressor's
+                                // threshold) are still worth keeping
+                                if dsp == crate::audio::dsp::DspSettings::default() {
+                                    song.track_dsp.remove(&track_id);
+                                } else {
+                                    song.track_dsp.insert(track_id, dsp);
+                                }
+                                if song.track_dsp.is_empty() {
+                                    self.config.songs.remove(&key);
+                                }
+                                self.mark_config_dirty();
+                            }
+                        }
                     }
                 }
+
+                let lyrics = self.lyrics_window.as_ref().and_then(|window| window.lyrics());
+                if let Some(crate::ui::practice::PracticeAction::CountInToggled(count_in)) = crate::ui::practice::render(
+                    ui,
+                    &self.audio_engine,
+                    &self.playback_state,
+                    lyrics,
+                    &mut self.practice,
+                    self.config.count_in,
+                ) {
+                    self.config.count_in = count_in;
+                    self.mark_config_dirty();
+                    self.apply_count_in();
+                }
+
+                if let Some(up_next) = self.up_next()
+                    && let Some(action) = crate::ui::queue::render_up_next(ui, &up_next)
+                {
+                    self.interstitial_action(action);
+                }
+
+                if let Some((message, _)) = &self.device_failure {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("⚠ No sound: {} (trying again)", message));
+                }
+
+                if let Some((path, progress)) = self.loader.progress() {
+                    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                    ui.add(egui::ProgressBar::new(progress).text(format!("Loading {}", name)));
+                }
+
+                if let Some(job) = &self.mixdown {
+                    let progress = f32::from_bits(job.progress.load(std::sync::atomic::Ordering::Relaxed));
+                    let name = job.output.file_name().unwrap_or_default().to_string_lossy();
+                    ui.add(egui::ProgressBar::new(progress).text(format!("Rendering {}", name)));
+                }
             });
 
             // Bottom section - Library (2/3) and Queue (1/3)
@@ -340,13 +587,7 @@ impl eframe::App for App {
                             ) {
                                 match action {
                                     crate::ui::library_view::LibraryAction::Load(path) => {
-                                        match self.load_song(path) {
-                                            Ok(_) => {
-                                            }
-                                            Err(e) => {
-                                                eprintln!("Failed to load song: {}", e);
-                                            }
-                                        }
+                                        self.load_song(path);
                                     }
                                     crate::ui::library_view::LibraryAction::Enqueue(path) => {
                                         // Get song title from metadata
@@ -377,8 +618,9 @@ impl eframe::App for App {
                                     crate::ui::library_view::LibraryAction::Rescan => {
                                         if let Some(library_path) = &self.config.library_path {
                                             match crate::library::scan_library(library_path) {
-                                                Ok(songs) => {
+                                                Ok(mut songs) => {
                                                     println!("Rescanned library: found {} songs", songs.len());
+                                                    crate::library::keep_cached(&mut songs, &self.library_songs);
 
                                                     // Save registry
                                                     let library_path_buf = std::path::PathBuf::from(library_path);
@@ -396,9 +638,9 @@ impl eframe::App for App {
                                         }
                                     }
                                     crate::ui::library_view::LibraryAction::Edit(path) => {
-                                        match std::fs::read_to_string(&path) {
-                                            Ok(content) => {
-                                                self.editor_state.load(path, content);
+                                        match crate::lrx::encoding::read_to_string(&path) {
+                                            Ok(decoded) => {
+                                                self.editor_state.load(path, decoded);
                                                 self.show_editor_window = true;
                                             }
                                             Err(e) => {
@@ -423,13 +665,10 @@ impl eframe::App for App {
 
                             if let Some(action) = crate::ui::queue::render(ui, &self.queue, is_playing) {
                                 match action {
-                                    crate::ui::queue::QueueAction::Load(path) => {
-                                        match self.load_song(path) {
-                                            Ok(_) => {
-                                            }
-                                            Err(e) => {
-                                                eprintln!("Failed to load song: {}", e);
-                                            }
+                                    crate::ui::queue::QueueAction::Load(id) => {
+                                        if let Some(path) = self.queue.get(id).and_then(|e| e.lrx_path.clone()) {
+                                            self.queue.set_current(id);
+                                            self.load_song(path);
                                         }
                                     }
                                     crate::ui::queue::QueueAction::Edit(id) => {
@@ -500,6 +739,25 @@ impl eframe::App for App {
             }
         }
 
+        if let Some(dialog) = &self.resave_utf8_dialog
+            && let Some(confirmed) = crate::ui::encoding_dialog::render(ctx, dialog)
+        {
+            if confirmed {
+                match std::fs::write(&dialog.path, &dialog.content) {
+                    Ok(_) => {
+                        println!("Re-saved {} as UTF-8", dialog.path.display());
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to re-save file as UTF-8: {}", e);
+                    }
+                }
+            }
+            self.resave_utf8_dialog = None;
+        }
+
+        self.poll_loading();
+        self.poll_mixdown();
+
         // Request repaint for smooth UI updates
         ctx.request_repaint();
     }
@@ -534,4 +792,190 @@ impl App {
             }
         }
     }
+    /// Move playback to the sound device chosen in the config, without stopping
+    fn switch_audio_device(&mut self) {
+        if self.audio_output != crate::audio::output::OutputBackend::Device {
+            return;
+        }
+        match crate::audio::output::DeviceOutput::open(self.config.audio_device.as_deref()) {
+            Ok(output) => {
+                self.device_failure = None;
+                if let Err(e) = self.audio_engine.lock().unwrap().set_output(Box::new(output)) {
+                    eprintln!("Failed to resume on new audio device: {:#}", e);
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open audio device: {:#}", e);
+                // Carry on silently on the null output, so the transport and lyrics keep
+                // going, until the device can be opened again
+                if self.device_failure.is_none() {
+                    let output = crate::audio::output::VirtualOutput::null(self.config.virtual_channels);
+                    if let Err(e) = self.audio_engine.lock().unwrap().set_output(Box::new(output)) {
+                        eprintln!("Failed to resume on the null output: {:#}", e);
+                    }
+                }
+                let retry_at = std::time::Instant::now() + DEVICE_RETRY_INTERVAL;
+                self.device_failure = Some((format!("{:#}", e), retry_at));
+            }
+        }
+    }
+
+    /// React to what the audio engine reports, logging everything
+    fn handle_engine_event(&mut self, event: crate::audio::events::EngineEvent) {
+        use crate::audio::events::EngineEvent;
+
+        if event.is_problem() {
+            eprintln!("{}", event);
+        } else {
+            println!("{}", event);
+        }
+
+        match event {
+            EngineEvent::SongEnded => self.song_ended(),
+            // Reopen the chosen device, or the default one if it's gone for good
+            EngineEvent::DeviceLost { .. } => self.switch_audio_device(),
+            _ => {}
+        }
+    }
+
+    /// A song played to its end: tick off its queue entry and, in auto-advance
+    /// mode, count down to the next one
+    fn song_ended(&mut self) {
+        let from_queue = self.queue.current()
+            .is_some_and(|entry| entry.lrx_path.is_some() && entry.lrx_path == self.current_song_path);
+        if from_queue {
+            self.queue.mark_current_done();
+        }
+
+        if !self.config.auto_advance {
+            return;
+        }
+        let Some(next) = self.queue.upcoming() else {
+            println!("End of queue");
+            return;
+        };
+
+        let remaining = std::time::Duration::from_secs_f32(self.config.interstitial_seconds.max(0.0));
+        println!("Up next: {} – {}", next.singer_name, next.song_title);
+        self.interstitial = Some(Interstitial {
+            entry_id: next.id,
+            ends_at: Some(std::time::Instant::now() + remaining),
+            remaining,
+        });
+    }
+
+    /// Start the next song when the countdown runs out
+    fn tick_interstitial(&mut self) {
+        // The entry may have been deleted from the queue meanwhile, or the host
+        // may have started something else playing
+        let is_playing = self.playback_state.lock().unwrap().is_playing;
+        if is_playing || self.interstitial.as_ref().is_some_and(|i| self.queue.get(i.entry_id).is_none()) {
+            self.interstitial = None;
+        }
+        if self.interstitial.as_ref().and_then(|i| i.remaining()).is_some_and(|r| r.is_zero()) {
+            self.advance_queue();
+        }
+    }
+
+    /// Load and play the entry the interstitial is counting down to
+    fn advance_queue(&mut self) {
+        let Some(interstitial) = self.interstitial.take() else {
+            return;
+        };
+        let Some(lrx_path) = self.queue.get(interstitial.entry_id).and_then(|e| e.lrx_path.clone()) else {
+            return;
+        };
+        self.queue.set_current(interstitial.entry_id);
+        self.load_song(lrx_path.clone());
+        self.play_when_loaded = Some(lrx_path);
+    }
+
+    fn interstitial_action(&mut self, action: crate::ui::queue::UpNextAction) {
+        use crate::ui::queue::UpNextAction;
+
+        match action {
+            UpNextAction::StartNow => self.advance_queue(),
+            UpNextAction::Hold => {
+                if let Some(interstitial) = &mut self.interstitial
+                    && let Some(remaining) = interstitial.remaining()
+                {
+                    interstitial.remaining = remaining;
+                    interstitial.ends_at = None;
+                }
+            }
+            UpNextAction::Resume => {
+                if let Some(interstitial) = &mut self.interstitial {
+                    interstitial.ends_at = Some(std::time::Instant::now() + interstitial.remaining);
+                }
+            }
+            UpNextAction::Cancel => {
+                self.interstitial = None;
+                let over = std::time::Duration::from_millis(self.config.transport_fade_ms);
+                self.audio_engine.lock().unwrap().stop_intermission(over);
+            }
+        }
+    }
+
+    /// The intermission track to follow the current song: only when auto-advancing
+    /// to another queued song
+    fn intermission(&self) -> Option<crate::audio::Intermission> {
+        let path = self.config.intermission_track.as_ref()?;
+        if !self.config.auto_advance || self.queue.upcoming().is_none() {
+            return None;
+        }
+        Some(crate::audio::Intermission {
+            path: std::path::PathBuf::from(path),
+            crossfade: std::time::Duration::from_secs_f32(self.config.crossfade_seconds.max(0.0)),
+        })
+    }
+
+    /// What the interstitial shows, while there is one
+    fn up_next(&self) -> Option<crate::ui::lyrics_window::UpNext> {
+        let interstitial = self.interstitial.as_ref()?;
+        let entry = self.queue.get(interstitial.entry_id)?;
+        Some(crate::ui::lyrics_window::UpNext {
+            singer_name: entry.singer_name.clone(),
+            song_title: entry.song_title.clone(),
+            remaining: interstitial.remaining().map(|r| r.as_secs_f64()),
+        })
+    }
+
+    /// Render the current song, as currently mixed, to a WAV file next to its LRX file
+    fn start_mixdown(&mut self) {
+        if self.mixdown.is_some() {
+            return;
+        }
+        let Some(lrx_path) = &self.current_song_path else {
+            eprintln!("No song loaded to render");
+            return;
+        };
+
+        let mixdown = self.audio_engine.lock().unwrap().mixdown();
+        let output = crate::audio::mixdown::default_output_path(lrx_path);
+        let progress = Arc::new(std::sync::atomic::AtomicU32::new(0));
+
+        println!("Rendering mix to {}", output.display());
+        let handle = std::thread::spawn({
+            let output = output.clone();
+            let progress = progress.clone();
+            move || mixdown.render_to_wav(&output, |fraction| {
+                progress.store(fraction.to_bits(), std::sync::atomic::Ordering::Relaxed);
+            })
+        });
+
+        self.mixdown = Some(MixdownJob { output, progress, handle });
+    }
+
+    /// Report a finished mixdown
+    fn poll_mixdown(&mut self) {
+        if !self.mixdown.as_ref().is_some_and(|job| job.handle.is_finished()) {
+            return;
+        }
+        let job = self.mixdown.take().unwrap();
+        match job.handle.join() {
+            Ok(Ok(length)) => println!("Wrote {:.1}s mix to {}", length.as_secs_f64(), job.output.display()),
+            Ok(Err(e)) => eprintln!("Failed to render mix: {:#}", e),
+            Err(_) => eprintln!("Mix render thread panicked"),
+        }
+    }
 }
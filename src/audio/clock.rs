@@ -0,0 +1,243 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Check the time every this many frames to spot the start of a new output callback
+const CHECK_INTERVAL_FRAMES: u64 = 16;
+
+/// A pause this long between pulls means the mixer is in a new output callback
+const CALLBACK_GAP: Duration = Duration::from_millis(1);
+
+/// `latency_nanos` value meaning "one callback buffer"
+const BUFFER_LATENCY: u64 = u64::MAX;
+
+/// Playback clock driven by the frames the output device actually pulled
+///
+/// The output stream pulls a whole buffer of samples at the start of every
+/// callback, then plays it out over the following buffer period. The clock
+/// records when each callback (burst) started and how many frames came
+/// before it, interpolates between bursts using the sample rate, and takes
+/// the output latency from `set_latency` (one callback buffer if unset, which
+/// misses any buffering the driver does after the callback). If no new burst arrives (decoder
+/// stall, underrun, pause) the position stops advancing instead of drifting.
+/// Seeking the counted source restarts the count from the seek target.
+#[derive(Clone)]
+pub struct PlaybackClock {
+    shared: Arc<ClockShared>,
+    sample_rate: SampleRate,
+}
+
+struct ClockShared {
+    epoch: Instant,
+    offset_nanos: AtomicU64, // Song position of the first counted frame
+    frames: AtomicU64,
+    burst_start_frames: AtomicU64,
+    burst_start_nanos: AtomicU64, // Since epoch; 0 = nothing pulled yet
+    burst_len: AtomicU64,
+    latency_nanos: AtomicU64, // BUFFER_LATENCY = estimate from the callback size
+    exact: AtomicBool,        // Every frame counts as heard as soon as it's pulled
+    started: AtomicBool, // The output has pulled from the counted source (kept across seeks)
+}
+
+impl PlaybackClock {
+    /// `offset` is the song position of the first frame the clock will count
+    pub fn new(sample_rate: SampleRate, offset: Duration) -> Self {
+        Self {
+            shared: Arc::new(ClockShared {
+                epoch: Instant::now(),
+                offset_nanos: AtomicU64::new(offset.as_nanos() as u64),
+                frames: AtomicU64::new(0),
+                burst_start_frames: AtomicU64::new(0),
+                burst_start_nanos: AtomicU64::new(0),
+                burst_len: AtomicU64::new(0),
+                latency_nanos: AtomicU64::new(BUFFER_LATENCY),
+                exact: AtomicBool::new(false),
+                started: AtomicBool::new(false),
+            }),
+            sample_rate,
+        }
+    }
+
+    /// Wrap a source so its consumption drives this clock
+    pub fn counting<S: Source>(&self, source: S) -> CountingSource<S> {
+        CountingSource {
+            channels: source.channels().max(1) as u64,
+            input: source,
+            shared: self.shared.clone(),
+            samples: 0,
+            last_check: None,
+        }
+    }
+
+    /// Song position currently being heard
+    pub fn position(&self) -> Duration {
+        let shared = &self.shared;
+        let offset = Duration::from_nanos(shared.offset_nanos.load(Ordering::Acquire));
+        if shared.exact.load(Ordering::Relaxed) {
+            let frames = shared.frames.load(Ordering::Acquire);
+            return offset + Duration::from_secs_f64(frames as f64 / self.sample_rate as f64);
+        }
+
+        let burst_nanos = shared.burst_start_nanos.load(Ordering::Acquire);
+        if burst_nanos == 0 {
+            return offset;
+        }
+
+        let since_burst = shared.epoch.elapsed().saturating_sub(Duration::from_nanos(burst_nanos));
+        let burst_len = shared.burst_len.load(Ordering::Acquire);
+        let latency = match shared.latency_nanos.load(Ordering::Relaxed) {
+            BUFFER_LATENCY => burst_len,
+            nanos => self.frames_in(Duration::from_nanos(nanos)),
+        };
+        let frames = heard_frames(
+            shared.burst_start_frames.load(Ordering::Acquire),
+            burst_len,
+            self.frames_in(since_burst),
+            latency,
+        );
+
+        offset + Duration::from_secs_f64(frames as f64 / self.sample_rate as f64)
+    }
+
+    /// Whether the output has reached the counted source yet
+    pub fn has_started(&self) -> bool {
+        self.shared.started.load(Ordering::Acquire)
+    }
+
+    /// Time between the output pulling a frame and it being heard; `None`
+    /// estimates it as one callback buffer
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        let nanos = latency.map_or(BUFFER_LATENCY, |latency| (latency.as_nanos() as u64).min(BUFFER_LATENCY - 1));
+        self.shared.latency_nanos.store(nanos, Ordering::Relaxed);
+    }
+
+    /// Count exactly the frames pulled, without timing the output's callbacks;
+    /// for outputs stepped by hand, where wall time means nothing
+    pub fn set_exact(&self, exact: bool) {
+        self.shared.exact.store(exact, Ordering::Relaxed);
+    }
+
+    fn frames_in(&self, duration: Duration) -> u64 {
+        (duration.as_secs_f64() * self.sample_rate as f64) as u64
+    }
+}
+
+/// Frames heard by now: the frames before the current burst plus time played since
+/// it (at most one buffer), minus the frames still queued on the way to the speakers
+fn heard_frames(burst_start_frames: u64, burst_len: u64, elapsed_frames: u64, latency_frames: u64) -> u64 {
+    (burst_start_frames + elapsed_frames.min(burst_len)).saturating_sub(latency_frames)
+}
+
+/// Source wrapper that reports how many frames the output has consumed
+pub struct CountingSource<S> {
+    input: S,
+    shared: Arc<ClockShared>,
+    channels: u64,
+    samples: u64,
+    last_check: Option<Instant>,
+}
+
+impl<S: Source> Iterator for CountingSource<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        let sample = self.input.next()?;
+        if self.samples == 0 {
+            self.shared.started.store(true, Ordering::Release);
+        }
+        self.samples += 1;
+
+        if self.samples.is_multiple_of(self.channels) {
+            let frames = self.samples / self.channels;
+            self.shared.frames.store(frames, Ordering::Release);
+
+            if frames % CHECK_INTERVAL_FRAMES == 1 || self.last_check.is_none() {
+                let now = Instant::now();
+                let new_burst = self.last_check
+                    .is_none_or(|last| now.duration_since(last) >= CALLBACK_GAP);
+
+                if new_burst {
+                    let previous = self.shared.burst_start_frames.load(Ordering::Acquire);
+                    if frames > previous && self.last_check.is_some() {
+                        self.shared.burst_len.store(frames - previous, Ordering::Release);
+                    }
+                    let nanos = now.duration_since(self.shared.epoch).as_nanos().max(1) as u64;
+                    self.shared.burst_start_frames.store(frames, Ordering::Release);
+                    self.shared.burst_start_nanos.store(nanos, Ordering::Release);
+                }
+                self.last_check = Some(now);
+            }
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for CountingSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+
+        // Count again from the seek target
+        let shared = &self.shared;
+        shared.burst_start_nanos.store(0, Ordering::Release);
+        shared.burst_start_frames.store(0, Ordering::Release);
+        shared.burst_len.store(0, Ordering::Release);
+        shared.frames.store(0, Ordering::Release);
+        shared.offset_nanos.store(pos.as_nanos() as u64, Ordering::Release);
+        self.samples = 0;
+        self.last_check = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heard_frames() {
+        // Right after a 512 frame burst starts, the previous buffer is still playing
+        assert_eq!(heard_frames(4096, 512, 0, 512), 3584);
+        // Halfway through the buffer period
+        assert_eq!(heard_frames(4096, 512, 256, 512), 3840);
+        // Stalled: never runs past what was actually delivered
+        assert_eq!(heard_frames(4096, 512, 100_000, 512), 4096);
+        // Nothing delivered yet
+        assert_eq!(heard_frames(0, 0, 1000, 0), 0);
+        // A device that buffers more than one callback
+        assert_eq!(heard_frames(4096, 512, 256, 2048), 2304);
+    }
+
+    #[test]
+    fn test_counting_source_counts_frames() {
+        let clock = PlaybackClock::new(44100, Duration::from_secs(10));
+        let buffer = rodio::buffer::SamplesBuffer::new(2, 44100, vec![0.0; 2000]);
+        let consumed = clock.counting(buffer).count();
+
+        assert_eq!(consumed, 2000);
+        assert_eq!(clock.shared.frames.load(Ordering::Acquire), 1000);
+        assert!(clock.position() >= Duration::from_secs(10));
+
+        clock.set_exact(true);
+        assert_eq!(clock.position(), Duration::from_secs(10) + Duration::from_secs_f64(1000.0 / 44100.0));
+    }
+}
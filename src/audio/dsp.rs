@@ -0,0 +1,455 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Corner frequencies of the 3-band EQ
+const EQ_LOW_HZ: f64 = 200.0;
+const EQ_MID_HZ: f64 = 1000.0;
+const EQ_HIGH_HZ: f64 = 5000.0;
+
+/// Compressor envelope timing
+const COMP_ATTACK: Duration = Duration::from_millis(10);
+const COMP_RELEASE: Duration = Duration::from_millis(150);
+
+/// Look for changed settings every this many frames
+const SETTINGS_CHECK_FRAMES: u32 = 64;
+
+/// Per-track effects settings: vocal reduction, high-pass filter, 3-band EQ and
+/// compressor, in that order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspSettings {
+    pub vocal_reduction: bool,      // Take out what's panned center, for songs with only a full mix
+    pub vocal_reduction_amount: f32, // 0.0 (none) to 1.0 (all of the center above the crossover)
+    pub vocal_crossover_hz: f32,    // Center content below this (bass, kick) is kept
+    pub high_pass: bool,
+    pub high_pass_hz: f32,
+    pub eq_low_db: f32,
+    pub eq_mid_db: f32,
+    pub eq_high_db: f32,
+    pub compressor: bool,
+    pub comp_threshold_db: f32,
+    pub comp_ratio: f32,
+    pub comp_makeup_db: f32,
+}
+
+impl Default for DspSettings {
+    fn default() -> Self {
+        Self {
+            vocal_reduction: false,
+            vocal_reduction_amount: 1.0,
+            vocal_crossover_hz: 150.0,
+            high_pass: false,
+            high_pass_hz: 120.0,
+            eq_low_db: 0.0,
+            eq_mid_db: 0.0,
+            eq_high_db: 0.0,
+            compressor: false,
+            comp_threshold_db: -18.0,
+            comp_ratio: 3.0,
+            comp_makeup_db: 0.0,
+        }
+    }
+}
+
+impl DspSettings {
+    /// True when the chain would leave the audio untouched
+    pub fn is_bypassed(&self) -> bool {
+        !self.vocal_reduction && !self.high_pass && !self.compressor
+            && self.eq_low_db == 0.0 && self.eq_mid_db == 0.0 && self.eq_high_db == 0.0
+    }
+}
+
+/// DSP settings shared between the engine and the audio thread
+#[derive(Default)]
+pub struct DspControl {
+    settings: Mutex<DspSettings>,
+    version: AtomicU64,
+}
+
+impl DspControl {
+    pub fn settings(&self) -> DspSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: DspSettings) {
+        *self.settings.lock().unwrap() = settings;
+        self.version.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Second order IIR filter (direct form I)
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2], // a1, a2 (a0 normalized to 1)
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Filter from raw coefficients (e.g. the Audio EQ Cookbook), normalized by a0
+    pub fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            ..Default::default()
+        }
+    }
+
+    fn low_pass(sample_rate: f64, hz: f64) -> Self {
+        let (cos, alpha) = Self::angle(sample_rate, hz, std::f64::consts::FRAC_1_SQRT_2);
+        Self::normalized(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn high_pass(sample_rate: f64, hz: f64) -> Self {
+        let (cos, alpha) = Self::angle(sample_rate, hz, std::f64::consts::FRAC_1_SQRT_2);
+        Self::normalized(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn peaking(sample_rate: f64, hz: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::angle(sample_rate, hz, 0.7);
+        Self::normalized(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    fn shelf(sample_rate: f64, hz: f64, gain_db: f64, high: bool) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::angle(sample_rate, hz, std::f64::consts::FRAC_1_SQRT_2);
+        let root = 2.0 * a.sqrt() * alpha;
+        // The high shelf is the low shelf with the sign of cos flipped
+        let (sign, cos) = if high { (-1.0, -cos) } else { (1.0, cos) };
+        Self::normalized(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + root),
+                sign * 2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - root),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + root,
+                sign * -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    fn angle(sample_rate: f64, hz: f64, q: f64) -> (f64, f64) {
+        let w0 = 2.0 * PI * hz.min(sample_rate * 0.45) / sample_rate;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    pub fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+
+    /// Keep the filter's history but take another filter's coefficients
+    fn retune(&mut self, other: Biquad) {
+        self.b = other.b;
+        self.a = other.a;
+    }
+}
+
+/// Source wrapper running a track's effects chain
+pub struct Dsp<S> {
+    input: S,
+    control: Arc<DspControl>,
+    version: u64,
+    settings: DspSettings,
+    channels: usize,
+    sample_rate: f64,
+    filters: Vec<Vec<Biquad>>, // Per channel: high pass (if on), low shelf, mid peak, high shelf
+    vocal_low_pass: Biquad,    // Splits the bass off the center channel for vocal reduction
+    bypassed: bool,
+    frame: Vec<Sample>,
+    index: usize,
+    frames_until_check: u32,
+    envelope_db: f64,
+    attack: f64,
+    release: f64,
+}
+
+impl<S: Source> Dsp<S> {
+    pub fn new(input: S, control: Arc<DspControl>) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate() as f64;
+        let coefficient = |time: Duration| 1.0 - (-1.0 / (time.as_secs_f64() * sample_rate)).exp();
+
+        let mut dsp = Self {
+            input,
+            settings: control.settings(),
+            version: control.version.load(Ordering::Acquire),
+            control,
+            channels,
+            sample_rate,
+            filters: Vec::new(),
+            vocal_low_pass: Biquad::default(),
+            bypassed: true,
+            frame: Vec::with_capacity(channels),
+            index: 0,
+            frames_until_check: SETTINGS_CHECK_FRAMES,
+            envelope_db: -120.0,
+            attack: coefficient(COMP_ATTACK),
+            release: coefficient(COMP_RELEASE),
+        };
+        dsp.design_filters();
+        dsp
+    }
+
+    fn design_filters(&mut self) {
+        self.bypassed = self.settings.is_bypassed();
+        let s = &self.settings;
+        let rate = self.sample_rate;
+        let mut chain = Vec::new();
+        if s.high_pass {
+            chain.push(Biquad::high_pass(rate, s.high_pass_hz as f64));
+        }
+        // EQ bands at 0 dB are pass-through, so they stay in the chain and sliders never change its shape
+        chain.push(Biquad::shelf(rate, EQ_LOW_HZ, s.eq_low_db as f64, false));
+        chain.push(Biquad::peaking(rate, EQ_MID_HZ, s.eq_mid_db as f64));
+        chain.push(Biquad::shelf(rate, EQ_HIGH_HZ, s.eq_high_db as f64, true));
+
+        self.vocal_low_pass.retune(Biquad::low_pass(rate, s.vocal_crossover_hz as f64));
+
+        // Keep filter state when only the settings move, to avoid clicks while dragging
+        if self.filters.first().is_some_and(|f| f.len() == chain.len()) {
+            for channel in &mut self.filters {
+                for (filter, designed) in channel.iter_mut().zip(&chain) {
+                    filter.retune(*designed);
+                }
+            }
+        } else {
+            self.filters = vec![chain; self.channels];
+        }
+    }
+
+    fn check_settings(&mut self) {
+        let version = self.control.version.load(Ordering::Acquire);
+        if version == self.version {
+            return;
+        }
+        // Never block the audio thread; pick the change up next time if the UI holds the lock
+        let settings = match self.control.settings.try_lock() {
+            Ok(settings) => settings.clone(),
+            Err(_) => return,
+        };
+        self.settings = settings;
+        self.version = version;
+        self.design_filters();
+    }
+
+    /// Pull and process the next frame; false when the input has ended
+    fn next_frame(&mut self) -> bool {
+        self.frame.clear();
+        self.index = 0;
+        for _ in 0..self.channels {
+            match self.input.next() {
+                Some(sample) => self.frame.push(sample),
+                None if self.frame.is_empty() => return false,
+                None => self.frame.push(0.0),
+            }
+        }
+
+        self.frames_until_check -= 1;
+        if self.frames_until_check == 0 {
+            self.frames_until_check = SETTINGS_CHECK_FRAMES;
+            self.check_settings();
+        }
+
+        if self.bypassed {
+            return true;
+        }
+
+        if self.settings.vocal_reduction && self.frame.len() == 2 {
+            self.reduce_vocals();
+        }
+
+        for (sample, filters) in self.frame.iter_mut().zip(&mut self.filters) {
+            let filtered = filters.iter_mut().fold(*sample as f64, |s, f| f.process(s));
+            *sample = filtered as f32;
+        }
+
+        if self.settings.compressor {
+            let gain = self.compressor_gain();
+            self.frame.iter_mut().for_each(|sample| *sample *= gain);
+        }
+
+        true
+    }
+
+    /// Turn down the center of the stereo image (mid = L + R), where lead vocals
+    /// are usually mixed, leaving the sides and the center's bass alone
+    fn reduce_vocals(&mut self) {
+        let (left, right) = (self.frame[0] as f64, self.frame[1] as f64);
+        let mid = (left + right) / 2.0;
+        let side = (left - right) / 2.0;
+
+        // The low band plus the rest adds back up to the mid exactly
+        let bass = self.vocal_low_pass.process(mid);
+        let amount = self.settings.vocal_reduction_amount.clamp(0.0, 1.0) as f64;
+        let mid = bass + (mid - bass) * (1.0 - amount);
+
+        self.frame[0] = (mid + side) as f32;
+        self.frame[1] = (mid - side) as f32;
+    }
+
+    /// Feed-forward compressor on the frame's peak, with attack/release smoothing in dB
+    fn compressor_gain(&mut self) -> f32 {
+        let s = &self.settings;
+        let level = self.frame.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+        let level_db = 20.0 * (level as f64).max(1e-6).log10();
+
+        let coefficient = if level_db > self.envelope_db { self.attack } else { self.release };
+        self.envelope_db += (level_db - self.envelope_db) * coefficient;
+
+        let over = self.envelope_db - s.comp_threshold_db as f64;
+        let reduction_db = if over > 0.0 { over * (1.0 - 1.0 / s.comp_ratio.max(1.0) as f64) } else { 0.0 };
+        10f64.powf((s.comp_makeup_db as f64 - reduction_db) / 20.0) as f32
+    }
+}
+
+impl<S: Source> Iterator for Dsp<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        if self.index >= self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.index];
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Dsp<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.frame.clear();
+        self.index = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine_rms(hz: f32, settings: DspSettings) -> f32 {
+        let rate = 48000;
+        let samples: Vec<f32> = (0..rate)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * hz * i as f32 / rate as f32).sin())
+            .collect();
+        let control = Arc::new(DspControl::default());
+        control.set_settings(settings);
+
+        // Skip the first 100 ms while the filters settle
+        let out: Vec<f32> = Dsp::new(SamplesBuffer::new(1, rate, samples), control).skip(4800).collect();
+        (out.iter().map(|s| s * s).sum::<f32>() / out.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_bypass_is_transparent() {
+        let dry = sine_rms(440.0, DspSettings::default());
+        assert!((dry - 0.5 / 2f32.sqrt()).abs() < 1e-3);
+        assert!(DspSettings::default().is_bypassed());
+    }
+
+    #[test]
+    fn test_high_pass_cuts_lows() {
+        let settings = DspSettings { high_pass: true, high_pass_hz: 200.0, ..Default::default() };
+        let low = sine_rms(40.0, settings.clone());
+        let high = sine_rms(2000.0, settings);
+        assert!(low < 0.05, "{}", low);
+        assert!(high > 0.34, "{}", high);
+    }
+
+    #[test]
+    fn test_eq_bands() {
+        let dry = sine_rms(1000.0, DspSettings::default());
+        let boosted = sine_rms(1000.0, DspSettings { eq_mid_db: 6.0, ..Default::default() });
+        assert!((20.0 * (boosted / dry).log10() - 6.0).abs() < 0.2);
+
+        let shelved = sine_rms(50.0, DspSettings { eq_low_db: -12.0, ..Default::default() });
+        assert!(20.0 * (shelved / dry).log10() < -10.0);
+    }
+
+    /// RMS of the left channel after the chain, for a sine at `left_level`/`right_level`
+    fn stereo_sine_rms(hz: f32, left_level: f32, right_level: f32, settings: DspSettings) -> f32 {
+        let rate = 48000;
+        let samples: Vec<f32> = (0..rate)
+            .flat_map(|i| {
+                let x = (2.0 * std::f32::consts::PI * hz * i as f32 / rate as f32).sin();
+                [left_level * x, right_level * x]
+            })
+            .collect();
+        let control = Arc::new(DspControl::default());
+        control.set_settings(settings);
+
+        let out: Vec<f32> = Dsp::new(SamplesBuffer::new(2, rate, samples), control)
+            .skip(9600)
+            .step_by(2)
+            .collect();
+        (out.iter().map(|s| s * s).sum::<f32>() / out.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_vocal_reduction_removes_center() {
+        let settings = DspSettings { vocal_reduction: true, ..Default::default() };
+        let dry = stereo_sine_rms(1000.0, 0.5, 0.5, DspSettings::default());
+
+        // A centered voice is gone, a hard-panned guitar and centered bass stay
+        let voice = stereo_sine_rms(1000.0, 0.5, 0.5, settings.clone());
+        let panned = stereo_sine_rms(1000.0, 0.5, 0.0, settings.clone());
+        let bass = stereo_sine_rms(50.0, 0.5, 0.5, settings.clone());
+        assert!(voice < dry * 0.05, "{}", voice);
+        assert!(panned > dry * 0.45, "{}", panned);
+        assert!(bass > dry * 0.9, "{}", bass);
+
+        // Half strength takes the center down by half
+        let half = stereo_sine_rms(1000.0, 0.5, 0.5, DspSettings { vocal_reduction_amount: 0.5, ..settings });
+        assert!((half / dry - 0.5).abs() < 0.05, "{}", half / dry);
+    }
+
+    #[test]
+    fn test_compressor_reduces_loud_signal() {
+        let settings = DspSettings { compressor: true, comp_threshold_db: -20.0, comp_ratio: 4.0, ..Default::default() };
+        let compressed = sine_rms(440.0, settings);
+        // -6 dBFS peak is 14 dB over threshold; 4:1 takes off about 10.5 dB
+        assert!(compressed < 0.5 / 2f32.sqrt() / 2.5, "{}", compressed);
+    }
+}
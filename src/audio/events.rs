@@ -0,0 +1,61 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Something that happened in the audio engine. Subscribers (the app, auto-advance,
+/// logging, remote integrations) get every event through
+/// [`AudioEngine::subscribe`](super::AudioEngine::subscribe).
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    /// A song's tracks were opened and are ready to play
+    SongLoaded { tracks: usize, duration: Duration },
+    /// Playback started or resumed
+    Started { position: Duration },
+    Paused { position: Duration },
+    /// Stopped by the host (a song playing to its end sends `SongEnded` instead)
+    Stopped,
+    Seeked { position: Duration },
+    /// Playback reached the end of the A–B loop and jumped back to its start
+    Looped { position: Duration },
+    /// One stem ran out of audio; the others may still be playing
+    TrackFinished { track_id: String },
+    /// The song played to its end (or its `[end:]` point)
+    SongEnded,
+    /// A track couldn't be opened or decoded
+    DecodeError { message: String },
+    /// Something went wrong that playback works around, e.g. a seek done by
+    /// reloading the tracks or a stem whose length had to be guessed
+    Warning { message: String },
+    /// The output stopped working, e.g. the device was unplugged
+    DeviceLost { message: String },
+}
+
+impl EngineEvent {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::DecodeError { .. } | Self::DeviceLost { .. })
+    }
+
+    /// Errors and warnings, which belong in the error log
+    pub fn is_problem(&self) -> bool {
+        self.is_error() || matches!(self, Self::Warning { .. })
+    }
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SongLoaded { tracks, duration } => {
+                write!(f, "Song loaded: {} tracks, {:.1}s", tracks, duration.as_secs_f64())
+            }
+            Self::Started { position } => write!(f, "Playing from {:.1}s", position.as_secs_f64()),
+            Self::Paused { position } => write!(f, "Paused at {:.1}s", position.as_secs_f64()),
+            Self::Stopped => write!(f, "Stopped"),
+            Self::Seeked { position } => write!(f, "Seeked to {:.1}s", position.as_secs_f64()),
+            Self::Looped { position } => write!(f, "Looped back to {:.1}s", position.as_secs_f64()),
+            Self::TrackFinished { track_id } => write!(f, "Track '{}' finished", track_id),
+            Self::SongEnded => write!(f, "Song ended"),
+            Self::DecodeError { message } => write!(f, "Decode error: {}", message),
+            Self::Warning { message } => write!(f, "Warning: {}", message),
+            Self::DeviceLost { message } => write!(f, "Audio device lost: {}", message),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// A gain ramp shared between the engine and the audio thread. The engine sets
+/// a target and how quickly to get there; the audio thread moves towards it a
+/// frame at a time, so fades are smooth whatever the UI frame rate.
+pub struct FadeControl {
+    target: AtomicU32, // f32 bits
+    rate: AtomicU32,   // f32 bits, gain change per second (infinite = jump)
+    level: AtomicU32,  // f32 bits, gain of the last frame played
+}
+
+impl FadeControl {
+    pub fn new(level: f32) -> Self {
+        Self {
+            target: AtomicU32::new(level.to_bits()),
+            rate: AtomicU32::new(f32::INFINITY.to_bits()),
+            level: AtomicU32::new(level.to_bits()),
+        }
+    }
+
+    /// Ramp from the current level to `target` over `over`, or jump there if it's zero
+    pub fn fade_to(&self, target: f32, over: Duration) {
+        let distance = (target - self.level()).abs().max(f32::EPSILON);
+        let rate = if over.is_zero() { f32::INFINITY } else { distance / over.as_secs_f32() };
+        self.rate.store(rate.to_bits(), Ordering::Relaxed);
+        self.target.store(target.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Jump straight to `level`, e.g. to silence before fading in
+    pub fn set(&self, level: f32) {
+        self.rate.store(f32::INFINITY.to_bits(), Ordering::Relaxed);
+        self.target.store(level.to_bits(), Ordering::Relaxed);
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Gain currently applied on the audio thread
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    pub fn target(&self) -> f32 {
+        f32::from_bits(self.target.load(Ordering::Relaxed))
+    }
+
+    /// True once a fade to silence has finished
+    pub fn is_silent(&self) -> bool {
+        self.target() == 0.0 && self.level() == 0.0
+    }
+}
+
+/// Applies a [`FadeControl`]'s gain, ramping it per frame
+pub struct Faded<S> {
+    input: S,
+    control: Arc<FadeControl>,
+    channels: usize,
+    sample_rate: f32,
+    gain: f32,
+    channel: usize, // Channel of the next sample
+}
+
+impl<S: Source> Faded<S> {
+    pub fn new(input: S, control: Arc<FadeControl>) -> Self {
+        Self {
+            channels: input.channels().max(1) as usize,
+            sample_rate: input.sample_rate() as f32,
+            gain: control.level(),
+            input,
+            control,
+            channel: 0,
+        }
+    }
+
+    /// Move one frame's step towards the target
+    fn step(&mut self) {
+        let target = self.control.target();
+        let rate = f32::from_bits(self.control.rate.load(Ordering::Relaxed));
+        let step = rate / self.sample_rate;
+        self.gain = if self.gain < target {
+            (self.gain + step).min(target)
+        } else {
+            (self.gain - step).max(target)
+        };
+        self.control.level.store(self.gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl<S: Source> Iterator for Faded<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        if self.channel == 0 {
+            self.step();
+        }
+        self.channel = (self.channel + 1) % self.channels;
+        self.input.next().map(|sample| sample * self.gain)
+    }
+}
+
+impl<S: Source> Source for Faded<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.channel = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn test_ramps_per_frame() {
+        let control = Arc::new(FadeControl::new(0.0));
+        // 1 Hz sample rate makes the rate per frame the rate per second
+        let mut faded = Faded::new(SamplesBuffer::new(2, 1, vec![1.0; 12]), control.clone());
+
+        control.fade_to(1.0, Duration::from_secs(4));
+        let samples: Vec<f32> = faded.by_ref().take(8).collect();
+        assert_eq!(samples, vec![0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1.0, 1.0]);
+        assert_eq!(control.level(), 1.0);
+
+        control.fade_to(0.0, Duration::ZERO);
+        assert_eq!(faded.next(), Some(0.0));
+        assert!(control.is_silent());
+    }
+}
@@ -0,0 +1,364 @@
+use super::master::MasterControl;
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Length of one count-in click
+const CLICK_LENGTH: Duration = Duration::from_millis(30);
+
+/// Peak level of a count-in click, before the master volume
+const CLICK_LEVEL: f32 = 0.5;
+
+/// Seek slot value meaning "nothing to seek to"
+const NO_SEEK: u64 = u64::MAX;
+
+/// Beats counted in before each repetition of a loop, and how far apart they are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountIn {
+    pub beats: u32,
+    pub beat: Duration,
+}
+
+impl CountIn {
+    /// `beats` beats at `bpm` beats per minute
+    pub fn at_tempo(beats: u32, bpm: f64) -> Self {
+        Self { beats, beat: Duration::from_secs_f64(60.0 / bpm.max(1.0)) }
+    }
+}
+
+/// A–B loop region shared between the engine and the audio thread. Looping
+/// happens on the audio thread at the exact frame, so the jump is seamless
+/// whatever the UI frame rate. Seeks take the same route: the engine leaves
+/// the target here and the audio thread applies it on its next frame, so a
+/// seek never waits for the output to pull audio.
+pub struct LoopControl {
+    enabled: AtomicBool,
+    start_nanos: AtomicU64,
+    end_nanos: AtomicU64,
+    count_in_beats: AtomicU32,
+    beat_nanos: AtomicU64,
+    repetitions: AtomicU64, // Times playback has jumped back to the loop start
+    seek_nanos: AtomicU64,        // Seek waiting for the audio thread, or NO_SEEK
+    failed_seek_nanos: AtomicU64, // Seek the input couldn't do, or NO_SEEK
+}
+
+impl LoopControl {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            start_nanos: AtomicU64::new(0),
+            end_nanos: AtomicU64::new(0),
+            count_in_beats: AtomicU32::new(0),
+            beat_nanos: AtomicU64::new(0),
+            repetitions: AtomicU64::new(0),
+            seek_nanos: AtomicU64::new(NO_SEEK),
+            failed_seek_nanos: AtomicU64::new(NO_SEEK),
+        }
+    }
+
+    /// Loop between two song positions, or stop looping
+    pub fn set_region(&self, region: Option<(Duration, Duration)>) {
+        if let Some((start, end)) = region {
+            self.start_nanos.store(start.as_nanos() as u64, Ordering::Relaxed);
+            self.end_nanos.store(end.as_nanos() as u64, Ordering::Relaxed);
+        }
+        self.enabled.store(region.is_some(), Ordering::Release);
+    }
+
+    pub fn region(&self) -> Option<(Duration, Duration)> {
+        self.enabled.load(Ordering::Acquire).then(|| {
+            (
+                Duration::from_nanos(self.start_nanos.load(Ordering::Relaxed)),
+                Duration::from_nanos(self.end_nanos.load(Ordering::Relaxed)),
+            )
+        })
+    }
+
+    pub fn set_count_in(&self, count_in: Option<CountIn>) {
+        let count_in = count_in.unwrap_or(CountIn { beats: 0, beat: Duration::ZERO });
+        self.beat_nanos.store(count_in.beat.as_nanos() as u64, Ordering::Relaxed);
+        self.count_in_beats.store(count_in.beats, Ordering::Relaxed);
+    }
+
+    pub fn repetitions(&self) -> u64 {
+        self.repetitions.load(Ordering::Relaxed)
+    }
+
+    /// Seek the song to `position` on the next frame the output pulls
+    pub fn request_seek(&self, position: Duration) {
+        self.seek_nanos.store(to_nanos(position), Ordering::Release);
+    }
+
+    /// Seek target not applied yet (the output hasn't pulled since, e.g. while paused)
+    pub fn pending_seek(&self) -> Option<Duration> {
+        from_nanos(self.seek_nanos.load(Ordering::Acquire))
+    }
+
+    /// Forget seeks not applied yet or not done, e.g. when the mix is replaced
+    pub fn cancel_seek(&self) {
+        self.seek_nanos.store(NO_SEEK, Ordering::Release);
+        self.failed_seek_nanos.store(NO_SEEK, Ordering::Release);
+    }
+
+    /// Target of the last seek the input couldn't do, once
+    pub fn take_failed_seek(&self) -> Option<Duration> {
+        from_nanos(self.failed_seek_nanos.swap(NO_SEEK, Ordering::AcqRel))
+    }
+}
+
+impl Default for LoopControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Jumps back to the loop start when playback reaches the loop end, playing
+/// the count-in (if any) in between. Seeking the input restarts the playback
+/// clock, so it has to sit outside the clock's counting source; during the
+/// count-in nothing is pulled from the input and the clock holds at the loop start.
+pub struct Looping<S> {
+    input: S,
+    control: Arc<LoopControl>,
+    master: Arc<MasterControl>,
+    channels: usize,
+    sample_rate: SampleRate,
+    frame: u64,     // Song position of the next frame from the input
+    channel: usize, // Channel of the next sample
+    count_in: Option<(u64, u64)>, // Frame within the count-in, and its length in frames
+    click: f32,     // Current count-in sample, repeated on every channel
+}
+
+impl<S: Source> Looping<S> {
+    /// `position` is the song position the input starts at
+    pub fn new(input: S, control: Arc<LoopControl>, master: Arc<MasterControl>, position: Duration) -> Self {
+        let sample_rate = input.sample_rate();
+        Self {
+            channels: input.channels().max(1) as usize,
+            frame: to_frames(position, sample_rate),
+            input,
+            control,
+            master,
+            sample_rate,
+            channel: 0,
+            count_in: None,
+            click: 0.0,
+        }
+    }
+
+    /// Start and end of the loop in frames, if looping
+    fn region_frames(&self) -> Option<(u64, u64)> {
+        let (start, end) = self.control.region()?;
+        let (start, end) = (to_frames(start, self.sample_rate), to_frames(end, self.sample_rate));
+        (start < end).then_some((start, end))
+    }
+
+    /// Go back to the loop start; false if the input can't seek, which is reported
+    /// like a failed seek so the engine reloads at the loop start instead
+    fn jump_back(&mut self, start: u64) -> bool {
+        let position = Duration::from_secs_f64(start as f64 / self.sample_rate as f64);
+        if self.input.try_seek(position).is_err() {
+            self.control.failed_seek_nanos.store(to_nanos(position), Ordering::Release);
+            return false;
+        }
+        self.frame = start;
+        self.control.repetitions.fetch_add(1, Ordering::Relaxed);
+
+        let beats = self.control.count_in_beats.load(Ordering::Relaxed) as u64;
+        let beat = Duration::from_nanos(self.control.beat_nanos.load(Ordering::Relaxed));
+        let length = beats * to_frames(beat, self.sample_rate);
+        self.count_in = (length > 0).then_some((0, length));
+        true
+    }
+
+    /// Count-in sample at `frame`: a short decaying beep on every beat, higher on the first
+    fn click_at(&self, frame: u64) -> f32 {
+        let beat = to_frames(Duration::from_nanos(self.control.beat_nanos.load(Ordering::Relaxed)), self.sample_rate).max(1);
+        let (index, offset) = (frame / beat, frame % beat);
+        let length = to_frames(CLICK_LENGTH, self.sample_rate).max(1);
+        if offset >= length {
+            return 0.0;
+        }
+
+        let pitch = if index == 0 { 1500.0 } else { 1000.0 };
+        let t = offset as f32 / self.sample_rate as f32;
+        let envelope = 1.0 - offset as f32 / length as f32;
+        (std::f32::consts::TAU * pitch * t).sin() * envelope * CLICK_LEVEL * self.master.volume()
+    }
+}
+
+fn to_nanos(position: Duration) -> u64 {
+    (position.as_nanos() as u64).min(NO_SEEK - 1)
+}
+
+fn from_nanos(nanos: u64) -> Option<Duration> {
+    (nanos != NO_SEEK).then(|| Duration::from_nanos(nanos))
+}
+
+fn to_frames(position: Duration, sample_rate: SampleRate) -> u64 {
+    (position.as_secs_f64() * sample_rate as f64).round() as u64
+}
+
+impl<S: Source> Iterator for Looping<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        if self.channel == 0 {
+            if let Some(position) = from_nanos(self.control.seek_nanos.swap(NO_SEEK, Ordering::AcqRel))
+                && self.try_seek(position).is_err()
+            {
+                self.control.failed_seek_nanos.store(to_nanos(position), Ordering::Release);
+            }
+            if self.count_in.is_none()
+                && let Some((start, end)) = self.region_frames()
+                && self.frame == end
+            {
+                self.jump_back(start);
+            }
+            if let Some((frame, length)) = self.count_in {
+                if frame < length {
+                    self.click = self.click_at(frame);
+                    self.count_in = Some((frame + 1, length));
+                } else {
+                    self.count_in = None;
+                }
+            }
+        }
+
+        let sample = if self.count_in.is_some() {
+            self.click
+        } else {
+            match self.input.next() {
+                Some(sample) => sample,
+                // The song ran out before the loop end (its length was overestimated)
+                None => match self.region_frames() {
+                    Some((start, end)) if self.channel == 0 && start < self.frame && self.frame < end => {
+                        if !self.jump_back(start) {
+                            return None;
+                        }
+                        return self.next();
+                    }
+                    _ => return None,
+                },
+            }
+        };
+
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.channel = 0;
+            if self.count_in.is_none() {
+                self.frame += 1;
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Looping<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.frame = to_frames(pos, self.sample_rate);
+        self.channel = 0;
+        self.count_in = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    /// Mono source at 10 Hz whose samples are their own frame numbers
+    fn counting_source(frames: usize) -> SamplesBuffer {
+        SamplesBuffer::new(1, 10, (0..frames).map(|i| i as f32).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_loops_between_points() {
+        let control = Arc::new(LoopControl::new());
+        control.set_region(Some((Duration::from_millis(200), Duration::from_millis(500))));
+        let master = Arc::new(MasterControl::new(1.0));
+        let looping = Looping::new(counting_source(10), control.clone(), master, Duration::ZERO);
+
+        let samples: Vec<f32> = looping.take(11).collect();
+        assert_eq!(samples, vec![0.0, 1.0, 2.0, 3.0, 4.0, 2.0, 3.0, 4.0, 2.0, 3.0, 4.0]);
+        assert_eq!(control.repetitions(), 2);
+    }
+
+    #[test]
+    fn test_counts_in_before_each_repetition() {
+        let control = Arc::new(LoopControl::new());
+        control.set_region(Some((Duration::from_millis(200), Duration::from_millis(400))));
+        control.set_count_in(Some(CountIn { beats: 2, beat: Duration::from_millis(300) }));
+        let master = Arc::new(MasterControl::new(1.0));
+        let mut looping = Looping::new(counting_source(10), control, master, Duration::ZERO);
+        looping.try_seek(Duration::from_millis(200)).unwrap();
+
+        // Two beats of three frames (one click frame each at 10 Hz), then the loop again
+        let samples: Vec<f32> = looping.by_ref().take(2 + 6 + 2).collect();
+        assert_eq!(&samples[..2], &[2.0, 3.0]);
+        assert_eq!(&samples[8..], &[2.0, 3.0]);
+        assert!(samples[2..8].iter().all(|s| s.abs() <= CLICK_LEVEL));
+
+        // Seeking drops a count-in in progress
+        looping.by_ref().take(3).count();
+        looping.try_seek(Duration::from_millis(700)).unwrap();
+        assert_eq!(looping.next(), Some(7.0));
+    }
+
+    #[test]
+    fn test_plays_through_past_loop_end() {
+        let control = Arc::new(LoopControl::new());
+        control.set_region(Some((Duration::from_millis(100), Duration::from_millis(300))));
+        let master = Arc::new(MasterControl::new(1.0));
+        // Starting after the loop end plays on to the end of the song
+        let mut looping = Looping::new(counting_source(6), control, master, Duration::ZERO);
+        looping.try_seek(Duration::from_millis(400)).unwrap();
+        assert_eq!(looping.collect::<Vec<_>>(), vec![4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_reports_loop_it_cannot_jump_back_in() {
+        let control = Arc::new(LoopControl::new());
+        control.set_region(Some((Duration::from_millis(200), Duration::from_millis(400))));
+        let master = Arc::new(MasterControl::new(1.0));
+        // Buffered sources can't seek; it plays on past the loop end until reloaded
+        let looping = Looping::new(counting_source(6).buffered(), control.clone(), master, Duration::ZERO);
+        assert_eq!(looping.collect::<Vec<_>>(), vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(control.take_failed_seek(), Some(Duration::from_millis(200)));
+        assert_eq!(control.repetitions(), 0);
+    }
+
+    #[test]
+    fn test_seeks_on_next_frame() {
+        let control = Arc::new(LoopControl::new());
+        let master = Arc::new(MasterControl::new(1.0));
+        let mut looping = Looping::new(counting_source(10), control.clone(), master, Duration::ZERO);
+        assert_eq!(looping.next(), Some(0.0));
+
+        control.request_seek(Duration::from_millis(600));
+        assert_eq!(control.pending_seek(), Some(Duration::from_millis(600)));
+        assert_eq!(looping.next(), Some(6.0));
+        assert_eq!(control.pending_seek(), None);
+        assert_eq!(control.take_failed_seek(), None);
+    }
+}
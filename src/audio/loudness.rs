@@ -0,0 +1,225 @@
+use super::dsp::Biquad;
+use super::mixer::{StemMixer, TrackControl};
+use anyhow::Result;
+use rodio::Source;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Most a quiet song is boosted by normalization; the master limiter catches the peaks
+pub const MAX_BOOST_DB: f64 = 12.0;
+
+/// Most a loud song is cut by normalization
+pub const MAX_CUT_DB: f64 = 24.0;
+
+/// Blocks quieter than this never count towards integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks more than this far below the ungated loudness are ignored
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Report analysis progress every this many samples
+const PROGRESS_INTERVAL_SAMPLES: usize = 1 << 18;
+
+/// The two-stage K-weighting filter of ITU-R BS.1770 (high shelf, then high pass),
+/// designed for the given sample rate
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    use std::f64::consts::PI;
+
+    // Stage 1: high shelf modelling the acoustic effect of the head
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::normalized(
+        [vh + vb * k / q + k * k, 2.0 * (k * k - vh), vh - vb * k / q + k * k],
+        [a0, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k],
+    );
+
+    // Stage 2: high pass (RLB weighting)
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let high_pass = Biquad::normalized(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / (1.0 + k / q + k * k), (1.0 - k / q + k * k) / (1.0 + k / q + k * k)],
+    );
+
+    [shelf, high_pass]
+}
+
+/// EBU R128 integrated loudness meter for interleaved samples
+///
+/// Mean squares are collected over 100 ms steps; gating blocks are 400 ms long
+/// (four steps, 75% overlap) and gated absolutely at -70 LUFS and relatively
+/// at 10 LU below the ungated level. All channels are weighted equally, which
+/// is what BS.1770 specifies for mono and stereo.
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    step_frames: usize,
+    step_sums: Vec<f64>, // Per channel, for the current step
+    step_pos: usize,     // Frames into the current step
+    channel: usize,      // Channel of the next sample
+    recent_steps: Vec<f64>, // Channel-summed mean squares of the last 3 steps
+    blocks: Vec<f64>,       // Channel-summed mean square of each 400 ms block
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            filters: vec![k_weighting(sample_rate as f64); channels],
+            step_frames: (sample_rate as usize / 10).max(1),
+            step_sums: vec![0.0; channels],
+            step_pos: 0,
+            channel: 0,
+            recent_steps: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        let filtered = self.filters[self.channel]
+            .iter_mut()
+            .fold(sample as f64, |s, filter| filter.process(s));
+        self.step_sums[self.channel] += filtered * filtered;
+
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.channel = 0;
+            self.step_pos += 1;
+            if self.step_pos == self.step_frames {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        let step: f64 = self.step_sums.iter().sum::<f64>() / self.step_frames as f64;
+        self.step_sums.iter_mut().for_each(|sum| *sum = 0.0);
+        self.step_pos = 0;
+
+        if self.recent_steps.len() == 3 {
+            let block = (self.recent_steps.iter().sum::<f64>() + step) / 4.0;
+            self.blocks.push(block);
+            self.recent_steps.remove(0);
+        }
+        self.recent_steps.push(step);
+    }
+
+    /// Integrated loudness in LUFS, or `None` if everything was silent or shorter than one block
+    pub fn integrated(&self) -> Option<f64> {
+        let absolute_gate = lufs_to_power(ABSOLUTE_GATE_LUFS);
+        let gated: Vec<f64> = self.blocks.iter().copied().filter(|b| *b > absolute_gate).collect();
+        if gated.is_empty() {
+            return None;
+        }
+
+        let relative_gate = mean(&gated) * 10f64.powf(-RELATIVE_GATE_LU / 10.0);
+        let gated: Vec<f64> = gated.into_iter().filter(|b| *b > relative_gate).collect();
+        Some(power_to_lufs(mean(&gated)))
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn lufs_to_power(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+/// Measure the integrated loudness of a source, calling `progress` with the
+/// fraction measured when the source's length is known
+pub fn measure<S: Source>(source: S, mut progress: impl FnMut(f32)) -> Option<f64> {
+    let total_samples = source.total_duration()
+        .map(|d| d.as_secs_f64() * source.sample_rate() as f64 * source.channels() as f64);
+    let mut meter = LoudnessMeter::new(source.channels() as usize, source.sample_rate());
+    for (index, sample) in source.enumerate() {
+        meter.push(sample);
+        if index % PROGRESS_INTERVAL_SAMPLES == 0
+            && let Some(total) = total_samples
+        {
+            progress((index as f64 / total).min(1.0) as f32);
+        }
+    }
+    progress(1.0);
+    meter.integrated()
+}
+
+/// Measure the integrated loudness of a song: its stems mixed at their LRX volumes,
+/// exactly as the engine would play them. `progress` gets the fraction analyzed.
+pub fn analyze_tracks(tracks: &[(PathBuf, f32)], progress: impl FnMut(f32)) -> Result<Option<f64>> {
+    let mut stems = Vec::new();
+    for (path, volume) in tracks {
+        stems.push((super::open_decoder(path)?, Arc::new(TrackControl::new(*volume))));
+    }
+
+    if stems.is_empty() {
+        return Ok(None);
+    }
+    Ok(measure(StemMixer::new(stems, 2), progress))
+}
+
+/// Measure the integrated loudness of the song an LRX file describes
+pub fn analyze_lrx(lrx: &crate::lrx::LrxFile, song_dir: &Path, progress: impl FnMut(f32)) -> Result<Option<f64>> {
+    let tracks: Vec<(PathBuf, f32)> = lrx.tracks
+        .values()
+        .map(|track| (song_dir.join(&track.source), track.volume))
+        .collect();
+    analyze_tracks(&tracks, progress)
+}
+
+/// Gain in dB that brings a song measured at `loudness` to `target` (both LUFS)
+pub fn normalization_gain_db(loudness: f64, target: f64) -> f64 {
+    (target - loudness).clamp(-MAX_CUT_DB, MAX_BOOST_DB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(channels: u16, amplitude: f32, seconds: f32) -> SamplesBuffer {
+        let rate = 48000;
+        let frames = (rate as f32 * seconds) as usize;
+        let samples = (0..frames)
+            .flat_map(|i| {
+                let s = amplitude * (2.0 * std::f32::consts::PI * 997.0 * i as f32 / rate as f32).sin();
+                std::iter::repeat_n(s, channels as usize)
+            })
+            .collect::<Vec<_>>();
+        SamplesBuffer::new(channels, rate, samples)
+    }
+
+    #[test]
+    fn test_sine_reference_levels() {
+        // A -20 dBFS 1 kHz tone on both stereo channels reads -20 LUFS
+        let stereo = measure(sine(2, 0.1, 3.0), |_| {}).unwrap();
+        assert!((stereo - -20.0).abs() < 0.1, "{}", stereo);
+
+        // On a single channel it is 3 dB quieter
+        let mono = measure(sine(1, 0.1, 3.0), |_| {}).unwrap();
+        assert!((mono - -23.01).abs() < 0.1, "{}", mono);
+    }
+
+    #[test]
+    fn test_silence_and_short_input() {
+        assert_eq!(measure(SamplesBuffer::new(2, 48000, vec![0.0; 96000]), |_| {}), None);
+        assert_eq!(measure(sine(2, 0.5, 0.2), |_| {}), None);
+    }
+
+    #[test]
+    fn test_normalization_gain() {
+        assert_eq!(normalization_gain_db(-10.0, -16.0), -6.0);
+        assert_eq!(normalization_gain_db(-30.0, -16.0), MAX_BOOST_DB);
+    }
+}
@@ -0,0 +1,258 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Highest sample level the limiter lets through (about -0.3 dBFS)
+pub const LIMITER_CEILING: f32 = 0.966;
+
+/// How long the limiter takes to recover from gain reduction
+const LIMITER_RELEASE: Duration = Duration::from_millis(150);
+
+/// Publish limiter readings to the engine every this many frames
+const PUBLISH_INTERVAL_FRAMES: usize = 256;
+
+/// Master bus settings and readings shared between the engine and the audio thread
+pub struct MasterControl {
+    volume: AtomicU32,   // f32 bits
+    gain: AtomicU32,     // f32 bits, linear loudness normalization gain
+    min_gain: AtomicU32, // f32 bits, lowest limiter gain since last taken
+}
+
+impl MasterControl {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            volume: AtomicU32::new(volume.to_bits()),
+            gain: AtomicU32::new(1.0f32.to_bits()),
+            min_gain: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Set the loudness normalization gain, in dB, applied along with the master volume
+    pub fn set_gain_db(&self, db: f32) {
+        let gain = 10f32.powf(db / 20.0);
+        self.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Combined master volume and normalization gain
+    fn total_gain(&self) -> f32 {
+        self.volume() * f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    /// Lowest limiter gain since the last call (1.0 = not limiting), resetting it
+    pub fn take_min_gain(&self) -> f32 {
+        f32::from_bits(self.min_gain.swap(1.0f32.to_bits(), Ordering::Relaxed))
+    }
+}
+
+/// Master stage: applies the master volume and loudness normalization gain, then a
+/// brickwall limiter so the sum of several full-level stems never clips the output.
+///
+/// The limiter works a frame at a time with instant attack, so no sample ever leaves
+/// above [`LIMITER_CEILING`], and releases smoothly back to unity gain.
+pub struct MasterBus<S> {
+    input: S,
+    control: Arc<MasterControl>,
+    channels: usize,
+    frame: Vec<Sample>,
+    index: usize, // Next sample of `frame` to hand out
+    gain: f32,
+    release: f32, // Per-frame recovery coefficient
+    min_gain: f32,
+    unpublished_frames: usize,
+}
+
+impl<S: Source> MasterBus<S> {
+    pub fn new(input: S, control: Arc<MasterControl>) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let release_frames = LIMITER_RELEASE.as_secs_f32() * input.sample_rate() as f32;
+
+        Self {
+            release: 1.0 - (-1.0 / release_frames).exp(),
+            input,
+            control,
+            channels,
+            frame: Vec::with_capacity(channels),
+            index: 0,
+            gain: 1.0,
+            min_gain: 1.0,
+            unpublished_frames: 0,
+        }
+    }
+
+    /// Pull and process the next frame; false when the input has ended
+    fn next_frame(&mut self) -> bool {
+        self.frame.clear();
+        self.index = 0;
+
+        let volume = self.control.total_gain();
+        for _ in 0..self.channels {
+            match self.input.next() {
+                Some(sample) => self.frame.push(sample * volume),
+                None if self.frame.is_empty() => return false,
+                None => self.frame.push(0.0),
+            }
+        }
+
+        let level = self.frame.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        let target = if level > LIMITER_CEILING { LIMITER_CEILING / level } else { 1.0 };
+        self.gain = if target < self.gain {
+            target
+        } else {
+            self.gain + (target - self.gain) * self.release
+        };
+
+        for sample in &mut self.frame {
+            *sample = (*sample * self.gain).clamp(-LIMITER_CEILING, LIMITER_CEILING);
+        }
+
+        self.min_gain = self.min_gain.min(self.gain);
+        self.unpublished_frames += 1;
+        if self.unpublished_frames >= PUBLISH_INTERVAL_FRAMES {
+            self.publish();
+        }
+
+        true
+    }
+
+    fn publish(&mut self) {
+        let min_gain = self.min_gain;
+        let _ = self.control.min_gain.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some(f32::from_bits(bits).min(min_gain).to_bits())
+        });
+        self.min_gain = 1.0;
+        self.unpublished_frames = 0;
+    }
+}
+
+impl<S: Source> Iterator for MasterBus<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        if self.index >= self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.index];
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for MasterBus<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.frame.clear();
+        self.index = 0;
+        Ok(())
+    }
+}
+
+/// Applies the master volume alone, without normalization or limiting, to audio
+/// played beside the song (the intermission track), so volume changes reach it
+/// on the audio thread like they reach the mix
+pub struct MasterVolume<S> {
+    input: S,
+    control: Arc<MasterControl>,
+}
+
+impl<S: Source> MasterVolume<S> {
+    pub fn new(input: S, control: Arc<MasterControl>) -> Self {
+        Self { input, control }
+    }
+}
+
+impl<S: Source> Iterator for MasterVolume<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        self.input.next().map(|sample| sample * self.control.volume())
+    }
+}
+
+impl<S: Source> Source for MasterVolume<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn master(samples: Vec<f32>, volume: f32) -> (MasterBus<SamplesBuffer>, Arc<MasterControl>) {
+        let control = Arc::new(MasterControl::new(volume));
+        (MasterBus::new(SamplesBuffer::new(2, 44100, samples), control.clone()), control)
+    }
+
+    #[test]
+    fn test_limiter_never_exceeds_ceiling() {
+        let (bus, _) = master(vec![1.8, -0.4, 0.5, -2.5, 0.2, 0.2], 1.0);
+        let out: Vec<f32> = bus.collect();
+
+        assert_eq!(out.len(), 6);
+        assert!(out.iter().all(|s| s.abs() <= LIMITER_CEILING));
+        // Both channels of a frame share one gain, keeping the stereo image
+        assert!((out[0] / out[1] - 1.8 / -0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_quiet_signal_passes_with_master_volume() {
+        let (bus, _) = master(vec![0.5, -0.5, 0.25, 0.0], 0.5);
+        let out: Vec<f32> = bus.collect();
+        assert_eq!(out, vec![0.25, -0.25, 0.125, 0.0]);
+    }
+
+    #[test]
+    fn test_limiter_readings() {
+        let (mut bus, control) = master(vec![1.5; 2 * PUBLISH_INTERVAL_FRAMES], 1.0);
+        assert!(bus.by_ref().count() > 0);
+
+        assert!(control.take_min_gain() < 1.0);
+        // Readings reset once taken
+        assert_eq!(control.take_min_gain(), 1.0);
+    }
+}
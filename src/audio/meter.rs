@@ -0,0 +1,158 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Publish readings to the meter every this many samples
+const PUBLISH_INTERVAL_SAMPLES: usize = 1024;
+
+/// Fraction of the displayed level kept per UI update when no louder reading arrives
+const DISPLAY_DECAY: f32 = 0.9;
+
+/// Peak and RMS level (1.0 = full scale)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Levels {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+impl Levels {
+    /// Fold in a new reading for display: rise instantly, fall back slowly
+    pub fn update(&mut self, reading: Levels) {
+        self.peak = reading.peak.max(self.peak * DISPLAY_DECAY);
+        self.rms = reading.rms.max(self.rms * DISPLAY_DECAY);
+    }
+}
+
+/// Level readings shared between a [`Metered`] source on the audio thread and the UI
+#[derive(Default)]
+pub struct LevelMeter {
+    peak: AtomicU32,        // f32 bits
+    sum_squares: AtomicU64, // f64 bits
+    samples: AtomicU64,
+}
+
+impl LevelMeter {
+    /// Levels since the last call, resetting the meter
+    pub fn take(&self) -> Levels {
+        let peak = f32::from_bits(self.peak.swap(0, Ordering::Relaxed));
+        let sum_squares = f64::from_bits(self.sum_squares.swap(0, Ordering::Relaxed));
+        let samples = self.samples.swap(0, Ordering::Relaxed);
+
+        let rms = if samples > 0 { (sum_squares / samples as f64).sqrt() as f32 } else { 0.0 };
+        Levels { peak, rms }
+    }
+
+    fn add(&self, peak: f32, sum_squares: f64, samples: usize) {
+        let _ = self.peak.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some(f32::from_bits(bits).max(peak).to_bits())
+        });
+        let _ = self.sum_squares.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + sum_squares).to_bits())
+        });
+        self.samples.fetch_add(samples as u64, Ordering::Relaxed);
+    }
+}
+
+/// Source wrapper that measures the level of everything passing through it
+pub struct Metered<S> {
+    input: S,
+    meter: Arc<LevelMeter>,
+    peak: f32,
+    sum_squares: f64,
+    samples: usize,
+}
+
+impl<S> Metered<S> {
+    pub fn new(input: S, meter: Arc<LevelMeter>) -> Self {
+        Self {
+            input,
+            meter,
+            peak: 0.0,
+            sum_squares: 0.0,
+            samples: 0,
+        }
+    }
+
+    fn publish(&mut self) {
+        self.meter.add(self.peak, self.sum_squares, self.samples);
+        self.peak = 0.0;
+        self.sum_squares = 0.0;
+        self.samples = 0;
+    }
+}
+
+impl<S: Source> Iterator for Metered<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        let Some(sample) = self.input.next() else {
+            if self.samples > 0 {
+                self.publish();
+            }
+            return None;
+        };
+
+        self.peak = self.peak.max(sample.abs());
+        self.sum_squares += (sample * sample) as f64;
+        self.samples += 1;
+        if self.samples >= PUBLISH_INTERVAL_SAMPLES {
+            self.publish();
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Metered<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn test_metered_levels() {
+        let meter = Arc::new(LevelMeter::default());
+        let samples = vec![0.5, -0.5, 0.5, -1.0];
+        let source = Metered::new(SamplesBuffer::new(2, 44100, samples.clone()), meter.clone());
+
+        // Passes samples through untouched
+        assert_eq!(source.collect::<Vec<_>>(), samples);
+
+        let levels = meter.take();
+        assert_eq!(levels.peak, 1.0);
+        assert!((levels.rms - (1.75f32 / 4.0).sqrt()).abs() < 1e-6);
+        assert_eq!(meter.take(), Levels::default());
+    }
+
+    #[test]
+    fn test_display_decay() {
+        let mut display = Levels::default();
+        display.update(Levels { peak: 1.0, rms: 0.5 });
+        display.update(Levels::default());
+        assert_eq!(display, Levels { peak: DISPLAY_DECAY, rms: 0.5 * DISPLAY_DECAY });
+    }
+}
@@ -0,0 +1,195 @@
+use super::dsp::DspSettings;
+use super::master::{MasterBus, MasterControl};
+use super::mixer::{StemMixer, StemSource, TrackControl};
+use anyhow::{Context, Result};
+use rodio::Source;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Report progress every this many frames
+const PROGRESS_INTERVAL_FRAMES: u64 = 48000;
+
+/// One stem of an offline render
+#[derive(Debug, Clone)]
+pub struct MixdownTrack {
+    pub id: String, // LRX track id
+    pub source: PathBuf,
+    pub gain: f32, // 0.0 for muted (or soloed-out) tracks
+    pub dsp: DspSettings,
+}
+
+/// Everything needed to render a song to a file without an audio device: the
+/// stems with their gains and effects, the master settings and the window to render.
+///
+/// Rendering runs the same mixer and master bus as playback, so a bounce sounds
+/// exactly like the song does in the player, including the fade-out at `end`.
+#[derive(Debug, Clone)]
+pub struct Mixdown {
+    pub tracks: Vec<MixdownTrack>,
+    pub start: Duration,
+    pub end: Option<Duration>,
+    pub master_volume: f32,
+    pub gain_db: f32, // Loudness normalization gain
+}
+
+impl Mixdown {
+    /// The song as its LRX file describes it: every track at its LRX volume, without
+    /// effects, over the `[start:]`/`[end:]` window
+    pub fn from_lrx(lrx: &crate::lrx::LrxFile, song_dir: &Path) -> Self {
+        let tracks = lrx.tracks
+            .values()
+            .map(|track| MixdownTrack {
+                id: track.id.clone(),
+                source: song_dir.join(&track.source),
+                gain: track.volume,
+                dsp: DspSettings::default(),
+            })
+            .collect();
+
+        let start = Duration::from_secs_f64(lrx.start().unwrap_or(0.0).max(0.0));
+        let end = lrx.end()
+            .map(|end| Duration::from_secs_f64(end.max(0.0)))
+            .filter(|end| *end > start);
+
+        Self {
+            tracks,
+            start,
+            end,
+            master_volume: 1.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Render to a 16-bit WAV file, calling `progress` with the fraction done along
+    /// the way. Returns the length of the rendered audio.
+    pub fn render_to_wav(&self, output: &Path, mut progress: impl FnMut(f32)) -> Result<Duration> {
+        if self.tracks.is_empty() {
+            anyhow::bail!("Song has no tracks to render");
+        }
+
+        let mut stems = Vec::new();
+        for track in &self.tracks {
+            let decoder = super::open_decoder(&track.source)?;
+            let control = Arc::new(TrackControl::new(track.gain));
+            control.dsp.set_settings(track.dsp.clone());
+            stems.push((Box::new(decoder.skip_duration(self.start)) as StemSource, control));
+        }
+
+        // Every track goes into the one stereo pair of the file, wherever it's routed live
+        let mixer = StemMixer::new(stems, 2);
+        // Stems have already skipped to the start, so their duration is what remains
+        let length = self.end
+            .map(|end| end.saturating_sub(self.start))
+            .or(mixer.total_duration());
+        let master = Arc::new(MasterControl::new(self.master_volume));
+        master.set_gain_db(self.gain_db);
+        let mix = MasterBus::new(mixer, master);
+
+        let channels = mix.channels();
+        let sample_rate = mix.sample_rate();
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(output, spec)
+            .with_context(|| format!("Failed to create {}", output.display()))?;
+
+        let to_frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64).round() as u64;
+        let total_frames = length.map(to_frames);
+        let fade_frames = to_frames(super::END_FADE).max(1);
+
+        let mut frames = 0u64;
+        let mut samples = mix.peekable();
+        while samples.peek().is_some() && total_frames.is_none_or(|total| frames < total) {
+            // Fade out over the last moments of a trimmed song, as the player does
+            let fade = match (self.end, total_frames) {
+                (Some(_), Some(total)) => ((total - frames) as f32 / fade_frames as f32).min(1.0),
+                _ => 1.0,
+            };
+
+            for sample in samples.by_ref().take(channels as usize) {
+                writer.write_sample(to_pcm16(sample * fade))?;
+            }
+
+            frames += 1;
+            if frames.is_multiple_of(PROGRESS_INTERVAL_FRAMES)
+                && let Some(total) = total_frames
+            {
+                progress(frames as f32 / total as f32);
+            }
+        }
+
+        writer.finalize()?;
+        progress(1.0);
+        Ok(Duration::from_secs_f64(frames as f64 / sample_rate as f64))
+    }
+}
+
+/// Convert a sample to 16-bit PCM, clipping anything beyond full scale
+pub(crate) fn to_pcm16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+/// Where a song's bounce goes unless told otherwise: next to its LRX file
+pub fn default_output_path(lrx_path: &Path) -> PathBuf {
+    let stem = lrx_path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    lrx_path.with_file_name(format!("{} (mix).wav", stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_stem(path: &Path, value: f32, frames: usize) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for _ in 0..frames {
+            writer.write_sample((value * i16::MAX as f32) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_render_mixes_stems_over_window() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-mixdown-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_stem(&dir.join("a.wav"), 0.25, 16000);
+        write_stem(&dir.join("b.wav"), 0.5, 16000);
+
+        let track = |name: &str, gain| MixdownTrack {
+            id: name.to_string(),
+            source: dir.join(name),
+            gain,
+            dsp: DspSettings::default(),
+        };
+        let mixdown = Mixdown {
+            tracks: vec![track("a.wav", 1.0), track("b.wav", 0.5)],
+            start: Duration::from_millis(500),
+            end: None,
+            master_volume: 1.0,
+            gain_db: 0.0,
+        };
+
+        let output = dir.join("mix.wav");
+        let length = mixdown.render_to_wav(&output, |_| {}).unwrap();
+        assert_eq!(length, Duration::from_millis(1500));
+
+        let samples: Vec<i16> = hound::WavReader::open(&output).unwrap()
+            .into_samples()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples.len(), 24000);
+        // 0.25 + 0.5 * 0.5
+        assert!(samples.iter().all(|s| (*s as f32 / i16::MAX as f32 - 0.5).abs() < 0.01));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+use super::dsp::{Dsp, DspControl};
+use super::meter::{LevelMeter, Metered};
+use rodio::source::{SeekError, UniformSourceIterator};
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Boxed decoder (or decoder chain) feeding one stem of the mix
+pub type StemSource = Box<dyn Source + Send>;
+
+/// Channels every stem is converted to before it's routed to an output pair
+const STEM_CHANNELS: ChannelCount = 2;
+
+/// Per-track settings shared between the engine and the mixer on the audio thread
+pub struct TrackControl {
+    gain: AtomicU32, // f32 bits
+    output_pair: AtomicU16, // 0 = output channels 1-2, 1 = channels 3-4, ...
+    finished: AtomicBool, // The stem has run out of audio
+    pub dsp: Arc<DspControl>,
+    pub meter: Arc<LevelMeter>, // Level after gain, as heard in the mix
+}
+
+impl TrackControl {
+    pub fn new(gain: f32) -> Self {
+        Self {
+            gain: AtomicU32::new(gain.to_bits()),
+            output_pair: AtomicU16::new(0),
+            finished: AtomicBool::new(false),
+            dsp: Arc::new(DspControl::default()),
+            meter: Arc::new(LevelMeter::default()),
+        }
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    pub fn set_gain(&self, gain: f32) {
+        self.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn output_pair(&self) -> u16 {
+        self.output_pair.load(Ordering::Relaxed)
+    }
+
+    pub fn set_output_pair(&self, pair: u16) {
+        self.output_pair.store(pair, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    fn set_finished(&self, finished: bool) {
+        self.finished.store(finished, Ordering::Relaxed);
+    }
+}
+
+/// Applies a track's current gain
+struct TrackGain<S> {
+    input: S,
+    control: Arc<TrackControl>,
+}
+
+impl<S: Source> Iterator for TrackGain<S> {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        self.input.next().map(|sample| sample * self.control.gain())
+    }
+}
+
+impl<S: Source> Source for TrackGain<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+struct StemInput {
+    source: Metered<TrackGain<Dsp<UniformSourceIterator<StemSource>>>>,
+    control: Arc<TrackControl>,
+    finished: bool,
+}
+
+/// Mixes all stems of a song into one source, pulling one frame from every
+/// stem in turn so they share a single sample clock and can never drift apart.
+/// Each stem runs through its effects chain, gain and level meter on the way in.
+///
+/// Stems are converted to stereo at a common sample rate (the highest among
+/// them), then each is added to its track's output channel pair. A pair the
+/// output doesn't have falls back to the first, so nothing goes unheard.
+/// A stem that ends early contributes silence; the mix ends when every stem
+/// has ended.
+pub struct StemMixer {
+    inputs: Vec<StemInput>,
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+    total_duration: Option<Duration>,
+    frame: Vec<Sample>,
+    index: usize, // Next sample of `frame` to hand out
+    start: Duration, // Song position the stems started at, or were last seeked to
+    frames: u64,     // Frames mixed since `start`
+}
+
+impl StemMixer {
+    /// Mixer producing `channels` output channels (at least 2)
+    pub fn new(stems: Vec<(StemSource, Arc<TrackControl>)>, channels: ChannelCount) -> Self {
+        let channels = channels.max(STEM_CHANNELS);
+        let sample_rate = stems.iter().map(|(s, _)| s.sample_rate()).max().unwrap_or(44100);
+        let total_duration = stems.iter()
+            .map(|(s, _)| s.total_duration())
+            .try_fold(Duration::ZERO, |longest, d| d.map(|d| longest.max(d)));
+
+        let inputs = stems.into_iter()
+            .map(|(source, control)| {
+                control.set_finished(false);
+                let uniform = UniformSourceIterator::new(source, STEM_CHANNELS, sample_rate);
+                let gained = TrackGain {
+                    input: Dsp::new(uniform, control.dsp.clone()),
+                    control: control.clone(),
+                };
+                StemInput {
+                    source: Metered::new(gained, control.meter.clone()),
+                    control,
+                    finished: false,
+                }
+            })
+            .collect();
+
+        Self {
+            inputs,
+            channels,
+            sample_rate,
+            total_duration,
+            frame: Vec::new(),
+            index: 0,
+            start: Duration::ZERO,
+            frames: 0,
+        }
+    }
+
+    /// The stems have already been skipped to `position` (the mixer counts from
+    /// there, to put them back there if a seek fails)
+    pub fn starting_at(mut self, position: Duration) -> Self {
+        self.start = position;
+        self
+    }
+
+    /// Song position of the next frame to mix, rounded up so seeking there doesn't
+    /// land a frame early
+    fn song_position(&self) -> Duration {
+        let nanos = (self.frames as u128 * 1_000_000_000).div_ceil(self.sample_rate as u128);
+        self.start + Duration::from_nanos(nanos as u64)
+    }
+
+    /// Mix the next frame; false when every stem has ended
+    fn next_frame(&mut self) -> bool {
+        self.frame.clear();
+        self.frame.resize(self.channels as usize, 0.0);
+        self.index = 0;
+        let pairs = self.channels / STEM_CHANNELS;
+        let mut any_playing = false;
+
+        for input in &mut self.inputs {
+            if input.finished {
+                continue;
+            }
+            let pair = input.control.output_pair();
+            let first = if pair < pairs { (pair * STEM_CHANNELS) as usize } else { 0 };
+
+            for channel in first..first + STEM_CHANNELS as usize {
+                match input.source.next() {
+                    Some(sample) => {
+                        self.frame[channel] += sample;
+                        any_playing = true;
+                    }
+                    None => {
+                        input.finished = true;
+                        input.control.set_finished(true);
+                        break;
+                    }
+                }
+            }
+        }
+
+        if any_playing {
+            self.frames += 1;
+        }
+        any_playing
+    }
+}
+
+impl Iterator for StemMixer {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        if self.index >= self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.index];
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl Source for StemMixer {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    /// Seek every stem to the same position. If a stem can't seek, the ones already
+    /// moved are put back where they were, so the stems stay in step either way.
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let before = self.song_position();
+        for index in 0..self.inputs.len() {
+            if let Err(e) = self.inputs[index].source.try_seek(pos) {
+                // These stems just seeked, so they can seek back
+                for input in &mut self.inputs[..index] {
+                    let _ = input.source.try_seek(before);
+                }
+                return Err(e);
+            }
+        }
+
+        for input in &mut self.inputs {
+            input.finished = false;
+            input.control.set_finished(false);
+        }
+        self.frame.clear();
+        self.index = 0;
+        self.start = pos;
+        self.frames = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn stem(samples: Vec<f32>, gain: f32) -> (StemSource, Arc<TrackControl>) {
+        (Box::new(SamplesBuffer::new(1, 44100, samples)), Arc::new(TrackControl::new(gain)))
+    }
+
+    #[test]
+    fn test_mixes_in_lockstep_with_gain() {
+        let mixer = StemMixer::new(vec![
+            stem(vec![0.5, 0.5, 0.5], 1.0),
+            stem(vec![0.25, 0.25], 0.5),
+        ], 2);
+        // Mono stems play on both channels
+        let mixed: Vec<f32> = mixer.collect();
+        assert_eq!(mixed, vec![0.625, 0.625, 0.625, 0.625, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_gain_changes_apply_immediately() {
+        let (source, control) = stem(vec![1.0; 4], 1.0);
+        let mut mixer = StemMixer::new(vec![(source, control.clone())], 2);
+
+        assert_eq!(mixer.by_ref().take(2).collect::<Vec<_>>(), vec![1.0, 1.0]);
+        control.set_gain(0.0);
+        assert_eq!(mixer.by_ref().take(2).collect::<Vec<_>>(), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_seek_restarts_finished_stems() {
+        let mut mixer = StemMixer::new(vec![stem(vec![0.5; 4], 1.0)], 2);
+        assert_eq!(mixer.by_ref().count(), 8);
+
+        assert!(mixer.inputs[0].control.is_finished());
+
+        mixer.try_seek(Duration::ZERO).unwrap();
+        assert!(!mixer.inputs[0].control.is_finished());
+        assert_eq!(mixer.next(), Some(0.5));
+    }
+
+    #[test]
+    fn test_failed_seek_keeps_stems_in_step() {
+        let seekable = stem((0..6).map(|i| i as f32).collect(), 1.0);
+        // Buffered sources can't seek
+        let fixed = SamplesBuffer::new(1, 44100, (0..6).map(|i| i as f32 * 10.0).collect::<Vec<_>>()).buffered();
+        let mut mixer = StemMixer::new(vec![seekable, (Box::new(fixed), Arc::new(TrackControl::new(1.0)))], 2);
+        assert_eq!(mixer.by_ref().take(4).collect::<Vec<_>>(), vec![0.0, 0.0, 11.0, 11.0]);
+
+        assert!(mixer.try_seek(Duration::from_secs_f64(4.0 / 44100.0)).is_err());
+        assert_eq!(mixer.collect::<Vec<_>>(), vec![22.0, 22.0, 33.0, 33.0, 44.0, 44.0, 55.0, 55.0]);
+    }
+
+    #[test]
+    fn test_routes_stems_to_output_pairs() {
+        let (click, click_control) = stem(vec![0.5; 2], 1.0);
+        click_control.set_output_pair(1);
+        let mixer = StemMixer::new(vec![stem(vec![0.25; 2], 1.0), (click, click_control.clone())], 4);
+        assert_eq!(mixer.collect::<Vec<_>>(), vec![0.25, 0.25, 0.5, 0.5, 0.25, 0.25, 0.5, 0.5]);
+
+        // A pair the output lacks falls back to the first
+        let (click, click_control) = stem(vec![0.5], 1.0);
+        click_control.set_output_pair(1);
+        let mixer = StemMixer::new(vec![(click, click_control)], 2);
+        assert_eq!(mixer.collect::<Vec<_>>(), vec![0.5, 0.5]);
+    }
+}
@@ -0,0 +1,1505 @@
+pub mod clock;
+pub mod dsp;
+pub mod events;
+pub mod fade;
+pub mod looping;
+pub mod loudness;
+pub mod master;
+pub mod meter;
+pub mod mixdown;
+pub mod mixer;
+pub mod opus;
+pub mod output;
+
+use anyhow::{Context, Result};
+use clock::PlaybackClock;
+use dsp::DspSettings;
+use events::EngineEvent;
+use fade::{FadeControl, Faded};
+use looping::{CountIn, LoopControl, Looping};
+use master::{MasterBus, MasterControl, MasterVolume};
+use meter::{LevelMeter, Levels, Metered};
+use mixdown::{Mixdown, MixdownTrack};
+use mixer::{StemMixer, StemSource, TrackControl};
+use output::{AudioOutput, OutputBackend};
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, Sink, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc;
+
+use std::time::{Duration, Instant};
+
+/// One stem of the loaded song; its gain is applied by the mixer
+pub struct Track {
+    pub id: String,
+    pub name: String,
+    pub duration: Duration,
+    pub source: PathBuf,
+    pub volume: f32,
+    muted: bool,
+    soloed: bool,
+    audible: bool, // False while muted, or while another track is soloed
+    levels: Levels,
+    control: Arc<TrackControl>,
+    finished: bool, // TrackFinished has been sent
+}
+
+impl Track {
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.update_gain();
+    }
+
+    pub fn get_volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn is_soloed(&self) -> bool {
+        self.soloed
+    }
+
+    pub fn dsp(&self) -> DspSettings {
+        self.control.dsp.settings()
+    }
+
+    /// Change the effects chain; takes effect on the audio thread within a few milliseconds
+    pub fn set_dsp(&mut self, settings: DspSettings) {
+        self.control.dsp.set_settings(settings);
+    }
+
+    /// Stereo pair of the output this track plays on (0 = channels 1-2, 1 = channels 3-4, ...)
+    pub fn output_pair(&self) -> u16 {
+        self.control.output_pair()
+    }
+
+    pub fn set_output_pair(&mut self, pair: u16) {
+        self.control.set_output_pair(pair);
+    }
+
+    /// Recent levels of this track in the mix (after volume, mute and solo), for display
+    pub fn levels(&self) -> Levels {
+        self.levels
+    }
+
+    /// Whether this looks like a vocal stem (id or name mentions vocals)
+    pub fn is_vocal(&self) -> bool {
+        [&self.id, &self.name].iter().any(|s| {
+            let s = s.to_lowercase();
+            ["vocal", "vox", "voice", "guide"].iter().any(|word| s.contains(word))
+        })
+    }
+
+    fn update_gain(&self) {
+        self.control.set_gain(if self.audible { self.volume } else { 0.0 });
+    }
+}
+
+/// File extensions of audio the library picks up as song tracks
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "ogg", "opus", "m4a", "mp4", "aac"];
+
+/// Codecs that can be decoded, for error messages
+const SUPPORTED_CODECS: &str = "MP3, FLAC, WAV, Ogg Vorbis, Opus, and AAC or ALAC in M4A/MP4";
+
+/// Open a decoder that knows the file length, so it can seek instead of decoding from the start
+fn open_decoder(path: &Path) -> Result<StemSource> {
+    // rodio's decoders don't do Opus, which can also hide in an .ogg
+    if sniff_opus(path) {
+        return Ok(Box::new(opus::OpusDecoder::open(path)?));
+    }
+
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+    match Decoder::try_from(file) {
+        Ok(decoder) => Ok(Box::new(decoder)),
+        Err(DecoderError::UnrecognizedFormat | DecoderError::NoStreams) => Err(anyhow::anyhow!(
+            "Unsupported codec: {} (supported: {})", path.display(), SUPPORTED_CODECS
+        )),
+        Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to decode audio file: {}", path.display()))),
+    }
+}
+
+/// True if the file is Ogg Opus (its first page carries the `OpusHead` header)
+fn sniff_opus(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut head = [0u8; 64];
+    let Ok(read) = File::open(path).and_then(|mut file| file.read(&mut head)) else {
+        return false;
+    };
+    let head = &head[..read];
+    head.starts_with(b"OggS") && head.windows(8).any(|w| w == b"OpusHead")
+}
+
+/// Open an audio file and read its duration from the decoder, scanning the file
+/// when the decoder doesn't know it
+pub fn probe_duration(path: &Path) -> Result<Duration> {
+    let source = open_decoder(path)?;
+    match source.total_duration() {
+        Some(duration) => Ok(duration),
+        None => scan_duration(path),
+    }
+}
+
+/// Find an audio file's length by reading through its packets without decoding
+/// them. For files whose headers don't say, like VBR MP3s without a Xing header.
+pub fn scan_duration(path: &Path) -> Result<Duration> {
+    use symphonia::core::errors::Error;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+    use symphonia::core::units::TimeBase;
+
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .with_context(|| format!("Failed to read audio file: {}", path.display()))?
+        .format;
+
+    let track = format.default_track()
+        .ok_or_else(|| anyhow::anyhow!("No audio stream in {}", path.display()))?;
+    let track_id = track.id;
+    let time_base = track.codec_params.time_base
+        .or_else(|| track.codec_params.sample_rate.map(|rate| TimeBase::new(1, rate)))
+        .ok_or_else(|| anyhow::anyhow!("Unknown sample rate in {}", path.display()))?;
+
+    // The end of the last packet is the length
+    let mut end = 0;
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => end = end.max(packet.ts() + packet.dur()),
+            Ok(_) => {}
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(Error::ResetRequired) => break,
+            Err(e) => {
+                return Err(anyhow::Error::new(e).context(format!("Failed to scan audio file: {}", path.display())));
+            }
+        }
+    }
+
+    let time = time_base.calc_time(end);
+    Ok(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+}
+
+/// Stems of a song with their decoders already opened, so the engine can switch
+/// to it without doing any file I/O
+pub struct OpenedTracks {
+    tracks: Vec<OpenedTrack>,
+    warnings: Vec<String>, // Sent as `Warning` events once the engine has the tracks
+}
+
+struct OpenedTrack {
+    id: String,
+    name: String,
+    path: PathBuf,
+    volume: f32,
+    duration: Option<Duration>, // None until known; see `resolve_durations`
+    decoder: StemSource,
+}
+
+impl OpenedTracks {
+    /// Fill in the lengths the decoders couldn't tell: what `known` says for the
+    /// file (e.g. cached from an earlier scan), else by scanning it, else `fallback`
+    /// (e.g. the LRX `[length:]` tag). Returns the files scanned and their lengths.
+    pub fn resolve_durations(
+        &mut self,
+        known: impl Fn(&Path) -> Option<Duration>,
+        fallback: Option<Duration>,
+    ) -> Vec<(PathBuf, Duration)> {
+        let mut scanned = Vec::new();
+        for track in self.tracks.iter_mut().filter(|t| t.duration.is_none()) {
+            track.duration = known(&track.path).or_else(|| {
+                match scan_duration(&track.path) {
+                    Ok(duration) => {
+                        scanned.push((track.path.clone(), duration));
+                        Some(duration)
+                    }
+                    Err(e) => {
+                        self.warnings.push(format!("Couldn't find the length of {}: {:#}", track.path.display(), e));
+                        fallback
+                    }
+                }
+            });
+        }
+        scanned
+    }
+}
+
+/// Open and probe the decoder of every track (id, name, source, volume), resolving
+/// relative sources against `base_dir`. This is the slow part of loading a song and
+/// may run on any thread; `progress` gets the fraction of tracks opened. Lengths
+/// the decoders don't know are left for `OpenedTracks::resolve_durations`.
+pub fn open_tracks(
+    track_infos: Vec<(String, String, PathBuf, f32)>,
+    base_dir: Option<&Path>,
+    mut progress: impl FnMut(f32),
+) -> Result<OpenedTracks> {
+    let count = track_infos.len();
+    let mut tracks = Vec::with_capacity(count);
+
+    for (index, (id, name, source, volume)) in track_infos.into_iter().enumerate() {
+        let path = match base_dir {
+            Some(base) if source.is_relative() => base.join(&source),
+            _ => source,
+        };
+
+        let decoder = open_decoder(&path)?;
+        let duration = decoder.total_duration();
+        tracks.push(OpenedTrack { id, name, path, volume, duration, decoder });
+        progress((index + 1) as f32 / count as f32);
+    }
+
+    Ok(OpenedTracks { tracks, warnings: Vec::new() })
+}
+
+/// How long before the end trim point the audio starts fading out
+const END_FADE: Duration = Duration::from_millis(750);
+
+/// Shortest A–B loop, so a slip of the mouse doesn't make a stutter
+const MIN_LOOP: Duration = Duration::from_millis(250);
+
+/// Track roles that belong on the monitor outputs (channels 3-4) rather than the PA
+const MONITOR_ROLES: &[&str] = &["click", "guide"];
+
+/// Output pair for a track with the given LRX `role`: click and guide tracks go to
+/// the second pair (channels 3-4), everything else to the first. The mixer falls
+/// back to the first pair on outputs with only two channels.
+pub fn default_output_pair(role: Option<&str>) -> u16 {
+    match role {
+        Some(role) if MONITOR_ROLES.contains(&role) => 1,
+        _ => 0,
+    }
+}
+
+/// Length of the silence queued ahead of each mix (one span of rodio's sink queue)
+const SINK_LEAD_IN_SAMPLES: usize = 512;
+
+/// How long the clip indicator stays lit after the limiter engaged
+const CLIP_HOLD: Duration = Duration::from_secs(2);
+
+/// Extra time allowed for a fade to reach silence before acting anyway (the
+/// output may not be pulling audio)
+const FADE_GRACE: Duration = Duration::from_millis(250);
+
+/// Music played, looped, between songs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intermission {
+    pub path: PathBuf,
+    pub crossfade: Duration, // Overlap with the end of the song; zero to start when it ends
+}
+
+/// What to do once a transport fade-out has reached silence
+enum AfterFade {
+    Pause,
+    Stop,
+    Seek(Duration),
+}
+
+struct PendingFade {
+    then: AfterFade,
+    deadline: Instant,
+}
+
+/// The intermission track playing on its own sink, beside the song
+struct IntermissionPlayer {
+    sink: Sink,
+    fade: Arc<FadeControl>,
+    crossfade: Duration, // As armed when it started; also how long it fades out under the next song
+    stop_deadline: Option<Instant>, // Fading out, to be dropped by then
+}
+
+
+pub struct AudioEngine {
+    output: Box<dyn AudioOutput>,
+    tracks: Vec<Track>,
+    sink: Option<Sink>, // Plays the mix of all tracks
+    started: bool, // The transport is running (playing or paused)
+    paused_at: Option<Duration>,
+    base_dir: Option<PathBuf>,
+    seek_position: Option<Duration>,
+    trim_start: Duration,
+    trim_end: Option<Duration>,
+    end_fading: bool, // The song fade is ramping down into the end trim point
+    clock: Option<PlaybackClock>,
+    output_latency: Option<Duration>, // None = estimate it from the output's buffer
+    master: Arc<MasterControl>,
+    master_meter: Arc<LevelMeter>,
+    master_levels: Levels,
+    clipped_at: Option<Instant>,
+    loudness_gain_db: f32,
+    fade: Arc<FadeControl>, // Transport fades of the song
+    transport_fade: Duration,
+    pending_fade: Option<PendingFade>,
+    intermission: Option<Intermission>, // Armed for the end of the current song
+    intermission_failed: bool,
+    intermission_player: Option<IntermissionPlayer>,
+    looping: Arc<LoopControl>, // A–B loop of the current song
+    loop_repetitions: u64,     // Repetitions already reported as `Looped` events
+    subscribers: Vec<mpsc::Sender<EngineEvent>>,
+    device_lost: bool, // DeviceLost has been sent for the current output
+    resume_on_output: bool, // Was playing when the song couldn't be reopened on a new output
+}
+
+impl AudioEngine {
+    pub fn new(backend: &OutputBackend, device: Option<&str>, virtual_channels: u16) -> Result<Self> {
+        let output = output::open(backend, device, virtual_channels)?;
+        println!("Audio output: {}", output.describe());
+        Ok(Self::with_output(output))
+    }
+
+    /// Engine playing through an already opened output
+    pub fn with_output(output: Box<dyn AudioOutput>) -> Self {
+        Self {
+            output,
+            tracks: Vec::new(),
+            sink: None,
+            started: false,
+            paused_at: None,
+            base_dir: None,
+            seek_position: None,
+            trim_start: Duration::ZERO,
+            trim_end: None,
+            end_fading: false,
+            clock: None,
+            output_latency: None,
+            master: Arc::new(MasterControl::new(1.0)),
+            master_meter: Arc::new(LevelMeter::default()),
+            master_levels: Levels::default(),
+            clipped_at: None,
+            loudness_gain_db: 0.0,
+            fade: Arc::new(FadeControl::new(1.0)),
+            transport_fade: Duration::ZERO,
+            pending_fade: None,
+            intermission: None,
+            intermission_failed: false,
+            intermission_player: None,
+            looping: Arc::new(LoopControl::new()),
+            loop_repetitions: 0,
+            subscribers: Vec::new(),
+            device_lost: false,
+            resume_on_output: false,
+        }
+    }
+
+    /// Receive every event the engine emits from now on
+    pub fn subscribe(&mut self) -> mpsc::Receiver<EngineEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: EngineEvent) {
+        // Subscribers that went away are forgotten
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Length of the gain ramps on play, pause, stop and seek (zero for instant)
+    pub fn set_transport_fade(&mut self, fade: Duration) {
+        self.transport_fade = fade;
+    }
+
+    /// Delay between the output pulling audio and it being heard, for the
+    /// playback clock; `None` estimates it as one buffer of the output stream
+    pub fn set_output_latency(&mut self, latency: Option<Duration>) {
+        self.output_latency = latency;
+        if let Some(clock) = &self.clock {
+            clock.set_latency(latency);
+        }
+    }
+
+    pub fn set_base_dir(&mut self, dir: PathBuf) {
+        self.base_dir = Some(dir);
+    }
+
+    /// Replace the loaded song with stems opened by `open_tracks`; nothing here
+    /// touches the disk, so this is quick enough to call with the engine locked
+    pub fn load_opened(&mut self, opened: OpenedTracks) {
+        // Clear existing tracks
+        self.stop_sink();
+        self.pending_fade = None;
+        self.tracks.clear();
+        self.started = false;
+        self.paused_at = None;
+        self.seek_position = None;
+        self.resume_on_output = false;
+        self.trim_start = Duration::ZERO;
+        self.trim_end = None;
+        self.end_fading = false;
+        self.clock = None;
+        self.loudness_gain_db = 0.0;
+        self.master.set_gain_db(0.0);
+        self.looping.set_region(None);
+
+        let mut stems = Vec::new();
+
+        for track in opened.tracks {
+            let control = Arc::new(TrackControl::new(track.volume));
+            stems.push((track.decoder, control.clone()));
+
+            self.tracks.push(Track {
+                id: track.id,
+                name: track.name,
+                duration: track.duration.unwrap_or(Duration::ZERO),
+                source: track.path,
+                volume: track.volume,
+                muted: false,
+                soloed: false,
+                audible: true,
+                levels: Levels::default(),
+                control,
+                finished: false,
+            });
+        }
+
+        if !stems.is_empty() {
+            self.start_mix(stems, Duration::ZERO);
+        }
+
+        let (tracks, duration) = (self.tracks.len(), self.duration());
+        self.emit(EngineEvent::SongLoaded { tracks, duration });
+        for message in opened.warnings {
+            self.emit(EngineEvent::Warning { message });
+        }
+    }
+
+    /// Queue the mix of the given stems on a fresh, paused sink whose
+    /// frames drive the playback clock from `position` onwards
+    fn start_mix(&mut self, stems: Vec<(StemSource, Arc<TrackControl>)>, position: Duration) {
+        let mixer = StemMixer::new(stems, self.output.channels()).starting_at(position);
+        let mix = MasterBus::new(mixer, self.master.clone());
+        let mix = Faded::new(mix, self.fade.clone());
+        let mix = Metered::new(mix, self.master_meter.clone());
+        let clock = PlaybackClock::new(mix.sample_rate(), position);
+        clock.set_latency(self.output_latency);
+        clock.set_exact(self.output.is_stepped());
+        self.looping.cancel_seek();
+
+        let sink = Sink::connect_new(self.output.mixer());
+        sink.pause(); // Will be unpaused by play()
+        // The sink's queue reads the first span of a new source in the format of the
+        // mono filler it idles on; a short silent lead-in takes that span instead of
+        // the mix, whose channels would otherwise land on the wrong outputs
+        sink.append(rodio::source::Zero::new_samples(mix.channels(), mix.sample_rate(), SINK_LEAD_IN_SAMPLES));
+        sink.append(Looping::new(clock.counting(mix), self.looping.clone(), self.master.clone(), position));
+
+        self.stop_sink();
+        self.sink = Some(sink);
+        self.clock = Some(clock);
+    }
+
+    /// Number of stereo pairs tracks can be routed to on the current output
+    pub fn output_pairs(&self) -> u16 {
+        (self.output.channels() / 2).max(1)
+    }
+
+    /// Switch to another output without stopping: the mix is re-created on the
+    /// new output at the current position, playing or paused as before
+    pub fn set_output(&mut self, output: Box<dyn AudioOutput>) -> Result<()> {
+        println!("Audio output: {}", output.describe());
+        if let Some(pending) = self.pending_fade.take() {
+            self.after_fade(pending.then);
+        }
+        // Taken before the old output goes, whose clock stops with it; an earlier
+        // swap that couldn't reopen the song still owes a resume
+        let position = self.position();
+        let playing = self.is_playing() || self.resume_on_output;
+        self.resume_on_output = false;
+
+        self.stop_sink();
+        // The intermission was playing on the old output; it isn't carried over
+        self.intermission_player = None;
+        self.output = output;
+        self.device_lost = false;
+
+        // Stopped, or paused with a reload already due on next play
+        if self.tracks.is_empty() || (self.seek_position.is_some() && !playing) {
+            return Ok(());
+        }
+
+        if let Err(e) = self.reload_at_position(position) {
+            // Hold the spot, and play from there once an output can open the song
+            self.clock = None;
+            self.seek_position = Some(position);
+            self.paused_at = Some(position);
+            self.started = false;
+            self.resume_on_output = playing;
+            return Err(e);
+        }
+        self.seek_position = None;
+        if playing {
+            self.paused_at = None;
+            self.started = true;
+            if let Some(sink) = &self.sink {
+                sink.play();
+            }
+        }
+        Ok(())
+    }
+
+    fn stop_sink(&mut self) {
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+    }
+
+    /// Restrict playback to a window of the song (from the LRX `start`/`end` tags).
+    /// Playback starts at `start`, and fades out and stops at `end`.
+    pub fn set_trim(&mut self, start: Duration, end: Option<Duration>) {
+        let end = end.filter(|end| *end > start);
+        self.trim_start = start;
+        self.trim_end = end;
+
+        // Next play begins at the start point
+        if !self.is_playing() && !self.is_paused() {
+            self.seek_position = (start > Duration::ZERO).then_some(start);
+        }
+    }
+
+    /// Start of the playable window
+    pub fn trim_start(&self) -> Duration {
+        self.trim_start
+    }
+
+    /// End of the playable window (the end trim point, or the full duration)
+    pub fn trim_end(&self) -> Duration {
+        let duration = self.duration();
+        match self.trim_end {
+            Some(end) if duration > Duration::ZERO => end.min(duration),
+            Some(end) => end,
+            None => duration,
+        }
+    }
+
+    /// Loop playback between two song positions (kept inside the playable window),
+    /// or stop looping with None. Playback that's past the loop end, e.g. after a
+    /// seek, carries on to the end of the song.
+    pub fn set_loop(&mut self, region: Option<(Duration, Duration)>) {
+        let region = region
+            .map(|(start, end)| (start.max(self.trim_start), end.min(self.trim_end())))
+            .filter(|(start, end)| *end >= *start + MIN_LOOP);
+        self.looping.set_region(region);
+    }
+
+    pub fn loop_region(&self) -> Option<(Duration, Duration)> {
+        self.looping.region()
+    }
+
+    /// Click a count-in before each repetition of the loop, or not
+    pub fn set_count_in(&mut self, count_in: Option<CountIn>) {
+        self.looping.set_count_in(count_in);
+    }
+
+    pub fn play(&mut self) {
+        if self.tracks.is_empty() {
+            return;
+        }
+        self.resume_on_output = false;
+
+        // Fade the intermission out under the song
+        let intermission_fade = self.intermission_player.as_ref()
+            .map_or(self.transport_fade, |p| p.crossfade.max(self.transport_fade));
+        self.stop_intermission(intermission_fade);
+
+        // Changed our mind halfway through fading out: fade back in from there
+        match self.pending_fade.take() {
+            Some(PendingFade { then: AfterFade::Seek(position), .. }) => {
+                self.after_fade(AfterFade::Seek(position));
+                return;
+            }
+            Some(_) => {
+                self.fade.fade_to(1.0, self.transport_fade);
+                return;
+            }
+            None => {}
+        }
+        self.fade.set(0.0);
+        self.fade.fade_to(1.0, self.transport_fade);
+
+        // If we have a seek position, reload tracks at that position
+        let position = if let Some(seek_pos) = self.seek_position.take() {
+            if let Err(e) = self.reload_at_position(seek_pos) {
+                // Try again from the same spot on the next play
+                self.seek_position = Some(seek_pos);
+                self.emit(EngineEvent::DecodeError { message: format!("{:#}", e) });
+                return;
+            }
+            self.paused_at = None;
+            seek_pos
+        } else if let Some(paused_at) = self.paused_at.take() {
+            // Resume from pause
+            paused_at
+        } else {
+            // Start from beginning of the playable window
+            self.trim_start
+        };
+        self.started = true;
+
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+        self.emit(EngineEvent::Started { position });
+    }
+
+    fn reload_at_position(&mut self, position: Duration) -> Result<()> {
+        if self.tracks.is_empty() {
+            return Ok(());
+        }
+
+        // Clamp position to valid range
+        let max_duration = self.tracks.iter()
+            .map(|t| t.duration)
+            .max()
+            .unwrap_or(Duration::ZERO);
+        let clamped_position = position.min(max_duration);
+
+        // Open all decoders first, so a failure leaves the current mix untouched
+        let mut stems = Vec::new();
+        for track in &self.tracks {
+            // Skip to position (skip_duration with ZERO is a no-op)
+            let source = open_decoder(&track.source)?.skip_duration(clamped_position);
+            stems.push((Box::new(source) as StemSource, track.control.clone()));
+        }
+
+        self.start_mix(stems, clamped_position);
+
+        Ok(())
+    }
+
+    /// Fade out, then pause
+    pub fn pause(&mut self) {
+        self.fade_out_then(AfterFade::Pause, self.transport_fade);
+    }
+
+    /// Fade out, then stop
+    pub fn stop(&mut self) {
+        self.fade_out_then(AfterFade::Stop, self.transport_fade);
+    }
+
+    /// Fade the song out over `over`, then stop (the host's way to end a song gracefully)
+    pub fn fade_out(&mut self, over: Duration) {
+        self.fade_out_then(AfterFade::Stop, over);
+    }
+
+    /// True while a fade-out before pausing, stopping or seeking is under way
+    pub fn is_fading_out(&self) -> bool {
+        self.pending_fade.is_some()
+    }
+
+    fn fade_out_then(&mut self, then: AfterFade, over: Duration) {
+        if over.is_zero() || !self.is_playing() {
+            self.pending_fade = None;
+            self.after_fade(then);
+            return;
+        }
+
+        let deadline = match &self.pending_fade {
+            // A stop on its way is final
+            Some(PendingFade { then: AfterFade::Stop, .. }) if !matches!(then, AfterFade::Stop) => return,
+            // Keep the deadline while scrubbing, so the seek happens at all
+            Some(pending) if matches!((&pending.then, &then), (AfterFade::Seek(_), AfterFade::Seek(_))) => pending.deadline,
+            _ => {
+                self.fade.fade_to(0.0, over);
+                Instant::now() + over + FADE_GRACE
+            }
+        };
+        self.pending_fade = Some(PendingFade { then, deadline });
+    }
+
+    fn after_fade(&mut self, then: AfterFade) {
+        match then {
+            AfterFade::Pause => {
+                if self.is_playing() {
+                    self.pause_now();
+                    let position = self.position();
+                    self.emit(EngineEvent::Paused { position });
+                }
+            }
+            AfterFade::Stop => {
+                if self.is_playing() || self.is_paused() {
+                    self.stop_now();
+                    self.emit(EngineEvent::Stopped);
+                }
+            }
+            AfterFade::Seek(position) => {
+                match self.seek_now(position) {
+                    Ok(()) => self.emit(EngineEvent::Seeked { position }),
+                    Err(e) => self.emit(EngineEvent::DecodeError { message: format!("{:#}", e) }),
+                }
+                self.fade.fade_to(1.0, self.transport_fade);
+            }
+        }
+    }
+
+    fn pause_now(&mut self) {
+        self.resume_on_output = false;
+        // Hold the position the output had actually played up to
+        if self.started && self.paused_at.is_none() {
+            self.paused_at = Some(self.clock_position());
+        }
+
+        if let Some(sink) = &self.sink {
+            sink.pause();
+        }
+    }
+
+    fn stop_now(&mut self) {
+        self.resume_on_output = false;
+        self.started = false;
+        self.paused_at = None;
+
+        // Set seek position to the start point so next play will reload tracks from beginning
+        self.seek_position = Some(self.trim_start);
+        self.end_fading = false;
+        self.stop_sink();
+    }
+
+    /// Fully reset and reload all tracks from scratch at position 0
+    pub fn reset(&mut self) -> Result<()> {
+        // Stop everything
+        self.pending_fade = None;
+        self.stop_now();
+
+        // If we have tracks, reload them all from the beginning
+        if self.tracks.is_empty() {
+            return Ok(());
+        }
+
+        let track_infos: Vec<_> = self.tracks.iter().map(|t| {
+            (t.id.clone(), t.name.clone(), t.source.clone(), t.volume)
+        }).collect();
+
+        // Keep the trim window across the reload
+        let (trim_start, trim_end) = (self.trim_start, self.trim_end);
+
+        // Lengths found by scanning last time still hold
+        let mut opened = open_tracks(track_infos, self.base_dir.as_deref(), |_| {})?;
+        opened.resolve_durations(|path| {
+            self.tracks.iter().find(|t| t.source == path).map(|t| t.duration).filter(|d| !d.is_zero())
+        }, None);
+        self.load_opened(opened);
+
+        self.set_trim(trim_start, trim_end);
+        Ok(())
+    }
+
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        if self.tracks.is_empty() {
+            return Ok(());
+        }
+
+        // Clamp position to the playable window (can't seek beyond duration)
+        let end = self.trim_end();
+        let clamped_position = if end > Duration::ZERO {
+            position.clamp(self.trim_start, end)
+        } else {
+            position.max(self.trim_start)
+        };
+
+        // Dip the level around the jump while playing
+        if self.is_playing() && !self.transport_fade.is_zero() {
+            self.fade_out_then(AfterFade::Seek(clamped_position), self.transport_fade);
+            return Ok(());
+        }
+        self.seek_now(clamped_position)?;
+        self.emit(EngineEvent::Seeked { position: clamped_position });
+        Ok(())
+    }
+
+    fn seek_now(&mut self, clamped_position: Duration) -> Result<()> {
+        // Until the output reaches the mix the sink is still on its lead-in, where a
+        // seek would land; queue the mix afresh at the position instead
+        if self.seek_position.is_none() && !self.clock.as_ref().is_some_and(|clock| clock.has_started()) {
+            let playing = self.is_playing();
+            self.reload_at_position(clamped_position)?;
+            if playing {
+                if let Some(sink) = &self.sink {
+                    sink.play();
+                }
+            } else {
+                self.paused_at = Some(clamped_position);
+            }
+            return Ok(());
+        }
+
+        // Seek the decoders in place on the audio thread, which restarts the clock
+        // from the target; playback carries on if it was playing
+        if self.seek_position.is_none() && self.sink.is_some() {
+            self.looping.request_seek(clamped_position);
+            if !self.is_playing() {
+                self.paused_at = Some(clamped_position);
+            }
+            return Ok(());
+        }
+
+        // Fall back to pausing and reloading the tracks at the position on next play
+        self.pause_now();
+        self.seek_position = Some(clamped_position);
+        self.paused_at = Some(clamped_position);
+        self.started = false;
+
+        Ok(())
+    }
+
+    /// Current song position, from the frames the output device has consumed
+    pub fn position(&self) -> Duration {
+        if let Some(PendingFade { then: AfterFade::Seek(position), .. }) = &self.pending_fade {
+            return *position;
+        }
+
+        if let Some(paused_at) = self.paused_at {
+            return paused_at;
+        }
+
+        if self.started {
+            return self.looping.pending_seek().unwrap_or_else(|| self.clock_position());
+        }
+
+        self.trim_start
+    }
+
+    /// Position of the playback clock, counted from the frames the output consumed
+    fn clock_position(&self) -> Duration {
+        self.clock.as_ref().map_or(self.trim_start, |clock| clock.position())
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.tracks.iter()
+            .map(|t| t.duration)
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.started && self.paused_at.is_none()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn tracks_mut(&mut self) -> &mut [Track] {
+        &mut self.tracks
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master.volume()
+    }
+
+    /// Volume of the song and the intermission track
+    pub fn set_master_volume(&self, volume: f32) {
+        self.master.set_volume(volume);
+    }
+
+    /// Arm (or disarm, with `None`) the intermission track to follow the current song
+    pub fn set_intermission(&mut self, intermission: Option<Intermission>) {
+        if self.intermission != intermission {
+            self.intermission = intermission;
+            self.intermission_failed = false;
+        }
+    }
+
+    pub fn is_intermission_playing(&self) -> bool {
+        self.intermission_player.as_ref().is_some_and(|p| p.stop_deadline.is_none())
+    }
+
+    /// Start the armed intermission track, fading in over `fade_in`
+    fn start_intermission(&mut self, fade_in: Duration) {
+        if self.is_intermission_playing() || self.intermission_failed {
+            return;
+        }
+        let Some(intermission) = &self.intermission else {
+            return;
+        };
+
+        let source = File::open(&intermission.path)
+            .map_err(anyhow::Error::new)
+            .and_then(|file| Ok(Decoder::new_looped(BufReader::new(file))?))
+            .with_context(|| format!("Failed to play intermission track {}", intermission.path.display()));
+        let source = match source {
+            Ok(source) => source,
+            Err(e) => {
+                self.intermission_failed = true;
+                self.emit(EngineEvent::DecodeError { message: format!("{:#}", e) });
+                return;
+            }
+        };
+
+        let crossfade = intermission.crossfade;
+        let fade = Arc::new(FadeControl::new(0.0));
+        fade.fade_to(1.0, fade_in);
+        let sink = Sink::connect_new(self.output.mixer());
+        sink.append(rodio::source::Zero::new_samples(source.channels(), source.sample_rate(), SINK_LEAD_IN_SAMPLES));
+        sink.append(MasterVolume::new(Faded::new(source, fade.clone()), self.master.clone()));
+        self.intermission_player = Some(IntermissionPlayer { sink, fade, crossfade, stop_deadline: None });
+    }
+
+    /// Fade the intermission track out over `over` and stop it
+    pub fn stop_intermission(&mut self, over: Duration) {
+        if let Some(player) = &mut self.intermission_player
+            && player.stop_deadline.is_none()
+        {
+            player.fade.fade_to(0.0, over);
+            player.stop_deadline = Some(Instant::now() + over + FADE_GRACE);
+        }
+    }
+
+    /// Loudness normalization gain for the loaded song, in dB (reset by `load_opened`)
+    pub fn set_loudness_gain_db(&mut self, db: f32) {
+        self.loudness_gain_db = db;
+        self.master.set_gain_db(db);
+    }
+
+    pub fn loudness_gain_db(&self) -> f32 {
+        self.loudness_gain_db
+    }
+
+    /// Snapshot of the current mix (gains, mutes, effects, master and trim window)
+    /// for rendering offline
+    pub fn mixdown(&self) -> Mixdown {
+        Mixdown {
+            tracks: self.tracks
+                .iter()
+                .map(|track| MixdownTrack {
+                    id: track.id.clone(),
+                    source: track.source.clone(),
+                    gain: track.control.gain(),
+                    dsp: track.dsp(),
+                })
+                .collect(),
+            start: self.trim_start,
+            end: self.trim_end,
+            master_volume: self.master.volume(),
+            gain_db: self.loudness_gain_db,
+        }
+    }
+
+    /// Recent output levels after the limiter, for display
+    pub fn master_levels(&self) -> Levels {
+        self.master_levels
+    }
+
+    /// Whether the limiter recently had to step in to stop the mix clipping
+    pub fn is_clipping(&self) -> bool {
+        self.clipped_at.is_some_and(|at| at.elapsed() < CLIP_HOLD)
+    }
+
+    pub fn set_track_muted(&mut self, index: usize, muted: bool) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.muted = muted;
+            self.apply_mute_solo();
+        }
+    }
+
+    pub fn set_track_soloed(&mut self, index: usize, soloed: bool) {
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.soloed = soloed;
+            self.apply_mute_solo();
+        }
+    }
+
+    /// The track to drop out when singing along: the first vocal stem
+    pub fn guide_vocal_index(&self) -> Option<usize> {
+        self.tracks.iter().position(|t| t.is_vocal())
+    }
+
+    /// Toggle mute on the guide vocal. Returns the new muted state, if there is a vocal track
+    pub fn toggle_guide_vocal(&mut self) -> Option<bool> {
+        let index = self.guide_vocal_index()?;
+        let muted = !self.tracks[index].muted;
+        self.set_track_muted(index, muted);
+        Some(muted)
+    }
+
+    /// Silence muted tracks, and all non-soloed tracks while any track is soloed.
+    /// Volumes are left alone, so they come back as they were on unmute/unsolo.
+    fn apply_mute_solo(&mut self) {
+        let any_solo = self.tracks.iter().any(|t| t.soloed);
+        for track in &mut self.tracks {
+            track.audible = !track.muted && (!any_solo || track.soloed);
+            track.update_gain();
+        }
+    }
+
+    /// Check if all tracks have finished playing
+    fn is_finished(&self) -> bool {
+        self.sink.as_ref().is_none_or(|sink| sink.empty())
+    }
+}
+
+impl AudioEngine {
+    /// Update the given playback state with current engine state
+    /// Also handles auto-stop when playback finishes, and sends the events only
+    /// noticed here (tracks and the song ending, the device going away)
+    pub fn update_playback_state(&mut self, state: &mut crate::app::PlaybackState) {
+        let mut ended = false;
+
+        // Finish a pause, stop or seek once its fade-out is silent
+        if let Some(pending) = &self.pending_fade
+            && (self.fade.is_silent() || Instant::now() >= pending.deadline)
+        {
+            let pending = self.pending_fade.take().unwrap();
+            self.after_fade(pending.then);
+        }
+
+        // The decoders couldn't seek in place; reload them at the target instead
+        if let Some(position) = self.looping.take_failed_seek() {
+            let message = format!("Seek not supported, reloading at {:.1}s", position.as_secs_f64());
+            self.emit(EngineEvent::Warning { message });
+            let playing = self.is_playing();
+            match self.reload_at_position(position) {
+                Ok(()) if playing => {
+                    if let Some(sink) = &self.sink {
+                        sink.play();
+                    }
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    // Try again on the next play
+                    self.emit(EngineEvent::DecodeError { message: format!("{:#}", e) });
+                    self.pause_now();
+                    self.seek_position = Some(position);
+                    self.paused_at = Some(position);
+                    self.started = false;
+                }
+            }
+        }
+
+        // Drop the intermission once it has faded out
+        if let Some(player) = &self.intermission_player
+            && let Some(deadline) = player.stop_deadline
+            && (player.fade.is_silent() || Instant::now() >= deadline)
+        {
+            self.intermission_player.take().unwrap().sink.stop();
+        }
+
+        // Check if playback has finished
+        if self.is_playing() && self.is_finished() {
+            // Auto-stop when all tracks finish
+            self.stop_now();
+            ended = true;
+        }
+
+        // Fade out into the end trim point, then stop there. With an intermission
+        // armed the song fades out under it over the crossfade instead.
+        let crossfade = self.intermission.as_ref()
+            .map(|i| i.crossfade)
+            .filter(|crossfade| !crossfade.is_zero() && !self.intermission_failed);
+        // Inside an A–B loop the song doesn't end, whatever the trim point
+        let looping = self.loop_region().is_some_and(|(_, end)| self.position() <= end);
+        if self.is_playing() && (self.trim_end.is_some() || crossfade.is_some()) {
+            let end = self.trim_end();
+            let position = self.position();
+
+            if position >= end && !looping {
+                self.stop_now();
+                ended = true;
+            } else {
+                let remaining = end.saturating_sub(position);
+                if let Some(crossfade) = crossfade
+                    && remaining <= crossfade
+                    && !looping
+                {
+                    self.start_intermission(remaining);
+                }
+
+                // The audio thread ramps down to silence right at the end point. Fading
+                // in (after a play or seek), wait until the level meets the ramp.
+                let fade_len = crossfade.map_or(END_FADE, |crossfade| crossfade.max(END_FADE));
+                let in_fade = !looping && remaining <= fade_len;
+                let gain = remaining.as_secs_f32() / fade_len.as_secs_f32();
+                if in_fade
+                    && self.pending_fade.is_none()
+                    && self.fade.target() != 0.0
+                    && self.fade.level() >= gain
+                {
+                    self.fade.fade_to(0.0, remaining);
+                    self.end_fading = true;
+                } else if !in_fade && self.end_fading {
+                    // Looping or moved back out of the fade
+                    self.end_fading = false;
+                    if self.pending_fade.is_none() {
+                        self.fade.fade_to(1.0, self.transport_fade);
+                    }
+                }
+            }
+        }
+
+        // Jumped back to the loop start
+        let repetitions = self.looping.repetitions();
+        if repetitions != self.loop_repetitions {
+            self.loop_repetitions = repetitions;
+            if let Some((start, _)) = self.loop_region() {
+                self.emit(EngineEvent::Looped { position: start });
+            }
+        }
+
+        // Stems that ran out of audio
+        let mut finished = Vec::new();
+        for track in &mut self.tracks {
+            let is_finished = track.control.is_finished();
+            if is_finished && !track.finished {
+                finished.push(track.id.clone());
+            }
+            track.finished = is_finished;
+        }
+        for track_id in finished {
+            self.emit(EngineEvent::TrackFinished { track_id });
+        }
+
+        if ended {
+            self.emit(EngineEvent::SongEnded);
+            self.start_intermission(self.transport_fade);
+        }
+
+        if !self.device_lost
+            && let Some(message) = self.output.failure()
+        {
+            self.device_lost = true;
+            self.emit(EngineEvent::DeviceLost { message });
+        }
+
+        // Meters
+        self.master_levels.update(self.master_meter.take());
+        for track in &mut self.tracks {
+            track.levels.update(track.control.meter.take());
+        }
+        if self.master.take_min_gain() < 1.0 {
+            self.clipped_at = Some(Instant::now());
+        }
+
+        state.position = self.position().as_secs_f64();
+        state.duration = self.duration().as_secs_f64();
+        state.start = self.trim_start().as_secs_f64();
+        state.end = self.trim_end().as_secs_f64();
+        state.is_playing = self.is_playing();
+        state.is_paused = self.is_paused();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Engine on the given output with one constant-level 2 second stem loaded
+    fn engine_with_song(dir: &Path, output: Box<dyn AudioOutput>, level: i16) -> AudioEngine {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join("stem.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..16000 {
+            writer.write_sample(level).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut engine = AudioEngine::with_output(output);
+        engine.load_opened(open_tracks(vec![("a".into(), "A".into(), path, 1.0)], None, |_| {}).unwrap());
+        engine
+    }
+
+    /// Engine with the song from `engine_with_song` on a stepped output, and what steps it
+    fn stepped_engine(dir: &Path) -> (AudioEngine, output::VirtualStepper) {
+        let output = output::VirtualOutput::stepped(2);
+        let stepper = output.stepper();
+        (engine_with_song(dir, Box::new(output), 0), stepper)
+    }
+
+    /// Step the output 10 ms at a time, updating the engine, until `done` or 3 s of audio
+    fn step_until(
+        engine: &mut AudioEngine,
+        stepper: &output::VirtualStepper,
+        mut done: impl FnMut(&mut AudioEngine) -> bool,
+    ) {
+        let mut state = crate::app::PlaybackState::default();
+        for _ in 0..300 {
+            engine.update_playback_state(&mut state);
+            if done(engine) {
+                return;
+            }
+            stepper.advance_by(Duration::from_millis(10));
+        }
+        panic!("gave up waiting after 3 s of audio");
+    }
+
+    #[test]
+    fn test_transport_on_null_output() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        assert_eq!(engine.position(), Duration::ZERO);
+        assert_eq!(engine.duration(), Duration::from_secs(2));
+
+        // Position follows the frames the virtual device consumes, after the sink's lead-in
+        engine.play();
+        stepper.advance_by(Duration::from_millis(200));
+        let position = engine.position();
+        assert!(position > Duration::from_millis(150) && position <= Duration::from_millis(200), "{:?}", position);
+
+        // Pausing holds the position the output had reached
+        engine.pause();
+        assert_eq!(engine.position(), position);
+        stepper.advance_by(Duration::from_millis(50));
+        assert_eq!(engine.position(), position);
+
+        engine.seek(Duration::from_secs(1)).unwrap();
+        assert_eq!(engine.position(), Duration::from_secs(1));
+        assert!(engine.is_paused());
+
+        // Switching outputs keeps the position and the paused state
+        let output = output::VirtualOutput::stepped(4);
+        let stepper = output.stepper();
+        engine.set_output(Box::new(output)).unwrap();
+        assert_eq!(engine.position(), Duration::from_secs(1));
+        assert!(engine.is_paused());
+        engine.play();
+        stepper.advance_by(Duration::from_millis(100));
+        let position = engine.position();
+        assert!(position > Duration::from_millis(1050) && position <= Duration::from_millis(1100), "{:?}", position);
+
+        // A seek while playing lands on the next frame pulled
+        engine.seek(Duration::from_millis(500)).unwrap();
+        assert_eq!(engine.position(), Duration::from_millis(500));
+        stepper.advance_by(Duration::from_millis(100));
+        let position = engine.position();
+        assert!(position.abs_diff(Duration::from_millis(600)) < Duration::from_millis(1), "{:?}", position);
+
+        engine.stop();
+        assert_eq!(engine.position(), Duration::ZERO);
+        assert!(!engine.is_playing());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_swap_that_fails_resumes_on_the_next() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-swap-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        engine.play();
+        stepper.advance_by(Duration::from_millis(300));
+        let position = engine.position();
+
+        // The song can't be reopened on the new output: held where it was heard
+        std::fs::rename(dir.join("stem.wav"), dir.join("away.wav")).unwrap();
+        let output = output::VirtualOutput::stepped(2);
+        let stepper = output.stepper();
+        assert!(engine.set_output(Box::new(output)).is_err());
+        stepper.advance_by(Duration::from_millis(300));
+        assert!(engine.is_paused());
+        assert_eq!(engine.position(), position);
+
+        // The next output that can open it picks up there, still playing
+        std::fs::rename(dir.join("away.wav"), dir.join("stem.wav")).unwrap();
+        let output = output::VirtualOutput::stepped(2);
+        let stepper = output.stepper();
+        engine.set_output(Box::new(output)).unwrap();
+        assert!(engine.is_playing());
+        assert_eq!(engine.position(), position);
+        stepper.advance_by(Duration::from_millis(200));
+        let played = engine.position() - position;
+        assert!(played > Duration::from_millis(100) && played <= Duration::from_millis(200), "{:?}", played);
+
+        // Paused songs stay paused across a swap
+        engine.pause();
+        step_until(&mut engine, &stepper, |engine| engine.is_paused());
+        let output = output::VirtualOutput::stepped(2);
+        let stepper = output.stepper();
+        engine.set_output(Box::new(output)).unwrap();
+        stepper.advance_by(Duration::from_millis(200));
+        assert!(engine.is_paused());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transport_fades() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-fade-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        engine.set_transport_fade(Duration::from_millis(30));
+        let settle = |engine: &mut AudioEngine| step_until(engine, &stepper, |engine| !engine.is_fading_out());
+
+        engine.play();
+        stepper.advance_by(Duration::from_millis(100));
+
+        // Pausing keeps playing until the fade-out is done
+        engine.pause();
+        assert!(engine.is_playing() && engine.is_fading_out());
+        settle(&mut engine);
+        assert!(engine.is_paused());
+        assert_eq!(engine.fade.level(), 0.0);
+
+        // Seeking while playing dips out, jumps, and comes back in
+        engine.play();
+        engine.seek(Duration::from_secs(1)).unwrap();
+        assert_eq!(engine.position(), Duration::from_secs(1));
+        settle(&mut engine);
+        assert!(engine.is_playing());
+        stepper.advance_by(Duration::from_millis(100));
+        assert!(engine.position() > Duration::from_secs(1));
+        assert_eq!(engine.fade.target(), 1.0);
+
+        // A panic fade-out ends in a stop, and pausing meanwhile doesn't cut it short
+        engine.fade_out(Duration::from_millis(50));
+        engine.pause();
+        settle(&mut engine);
+        assert!(!engine.is_playing() && !engine.is_paused());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fades_into_end_point() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-end-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        engine.set_trim(Duration::ZERO, Some(Duration::from_secs(1)));
+        engine.seek(Duration::from_millis(500)).unwrap();
+        engine.play();
+        stepper.advance_by(Duration::from_millis(50));
+        let mut state = crate::app::PlaybackState::default();
+        engine.update_playback_state(&mut state);
+
+        // The ramp carries on with nobody updating the engine, reaching silence at the end
+        let remaining = Duration::from_secs(1) - engine.position();
+        stepper.advance_by(remaining / 2);
+        assert!((engine.fade.level() - 0.5).abs() < 0.05, "{}", engine.fade.level());
+        step_until(&mut engine, &stepper, |engine| !engine.is_playing());
+        assert_eq!(engine.fade.level(), 0.0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_events() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-events-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        let events = engine.subscribe();
+
+        engine.reset().unwrap();
+        engine.play();
+        engine.pause();
+        engine.seek(Duration::from_millis(1900)).unwrap();
+        engine.play();
+        step_until(&mut engine, &stepper, |engine| !engine.is_playing());
+
+        let events: Vec<EngineEvent> = events.try_iter().collect();
+        assert!(matches!(events[0], EngineEvent::SongLoaded { tracks: 1, .. }), "{:?}", events);
+        assert!(matches!(events[1], EngineEvent::Started { .. }));
+        assert!(matches!(events[2], EngineEvent::Paused { .. }));
+        assert_eq!(events[3], EngineEvent::Seeked { position: Duration::from_millis(1900) });
+        assert_eq!(events[4], EngineEvent::Started { position: Duration::from_millis(1900) });
+        assert_eq!(events[5..], [EngineEvent::TrackFinished { track_id: "a".into() }, EngineEvent::SongEnded]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_loops_instead_of_ending() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-engine-loop-{}", std::process::id()));
+        let (mut engine, stepper) = stepped_engine(&dir);
+        let events = engine.subscribe();
+
+        // Too short to loop
+        engine.set_loop(Some((Duration::from_millis(1000), Duration::from_millis(1100))));
+        assert_eq!(engine.loop_region(), None);
+
+        // Clamped to the song, which would end after 0.2 s without the loop
+        engine.set_loop(Some((Duration::from_millis(1600), Duration::from_secs(5))));
+        assert_eq!(engine.loop_region(), Some((Duration::from_millis(1600), Duration::from_secs(2))));
+        engine.seek(Duration::from_millis(1800)).unwrap();
+        engine.play();
+        step_until(&mut engine, &stepper, |_| {
+            events.try_iter().any(|event| event == EngineEvent::Looped { position: Duration::from_millis(1600) })
+        });
+        assert!(engine.is_playing());
+        assert!(engine.position() < Duration::from_secs(2));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_routes_track_to_output_pair() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-routing-{}", std::process::id()));
+        let recording = dir.join("out.wav");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = output::VirtualOutput::stepped_file(recording.clone(), 4).unwrap();
+        let stepper = output.stepper();
+
+        let mut engine = engine_with_song(&dir, Box::new(output), i16::MAX / 4);
+        assert_eq!(engine.output_pairs(), 2);
+        engine.tracks_mut()[0].set_output_pair(1);
+        engine.play();
+        stepper.advance_by(Duration::from_millis(200));
+        drop(engine); // Finishes the recording
+
+        let mut reader = hound::WavReader::open(&recording).unwrap();
+        assert_eq!(reader.spec().channels, 4);
+        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        let loudest = |channel: usize| samples.iter().skip(channel).step_by(4).map(|s| s.abs()).max().unwrap();
+        assert_eq!((loudest(0), loudest(1)), (0, 0));
+        assert!(loudest(2) > 0 && loudest(3) > 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolves_unknown_durations() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-duration-{}", std::process::id()));
+        let path = dir.join("stem.wav");
+        drop(stepped_engine(&dir));
+        assert_eq!(scan_duration(&path).unwrap(), Duration::from_secs(2));
+
+        let mut opened = open_tracks(vec![("a".into(), "A".into(), path.clone(), 1.0)], None, |_| {}).unwrap();
+        let fallback = Some(Duration::from_secs(5));
+
+        // A cached length beats scanning
+        opened.tracks[0].duration = None;
+        assert!(opened.resolve_durations(|_| Some(Duration::from_secs(3)), fallback).is_empty());
+        assert_eq!(opened.tracks[0].duration.unwrap(), Duration::from_secs(3));
+
+        opened.tracks[0].duration = None;
+        assert_eq!(opened.resolve_durations(|_| None, fallback), vec![(path.clone(), Duration::from_secs(2))]);
+        assert_eq!(opened.tracks[0].duration.unwrap(), Duration::from_secs(2));
+
+        // The fallback is only for files that can't be scanned
+        std::fs::remove_file(&path).unwrap();
+        opened.tracks[0].duration = None;
+        assert!(opened.warnings.is_empty());
+        assert!(opened.resolve_durations(|_| None, fallback).is_empty());
+        assert_eq!(opened.tracks[0].duration.unwrap(), Duration::from_secs(5));
+        assert_eq!(opened.warnings.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_codec_error() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-codec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let garbage = dir.join("lead.m4a");
+        std::fs::write(&garbage, b"not audio at all").unwrap();
+        let error = open_decoder(&garbage).err().unwrap().to_string();
+        assert!(error.starts_with("Unsupported codec:"), "{}", error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use audiopus::coder::{Decoder as PacketDecoder, GenericCtl};
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals};
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::codecs::CODEC_TYPE_OPUS;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Opus always decodes at 48 kHz, whatever the original rate was
+const OPUS_RATE: u32 = 48_000;
+
+/// Frames in the longest Opus packet (120 ms)
+const MAX_PACKET_FRAMES: usize = 5760;
+
+/// Frames decoded and thrown away before a seek target, so the decoder has settled
+/// by the time the target plays (80 ms, as RFC 7845 recommends)
+const SEEK_PREROLL: u64 = 3840;
+
+/// Ogg Opus file decoded with libopus; symphonia reads the Ogg pages, which also
+/// gives the length and seeking
+pub struct OpusDecoder {
+    format: Box<dyn FormatReader>,
+    track_id: u32,
+    decoder: PacketDecoder,
+    channels: ChannelCount,
+    origin: u64, // Timestamp of the first frame to play: the stream's start plus pre-skip
+    frames: Option<u64>, // Length, when the last page says
+    buffer: Vec<Sample>, // Samples of the last decoded packet
+    index: usize,
+    start: u64, // Frames before this are dropped: set by seeking, which decodes up to the target
+}
+
+impl OpusDecoder {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let format = symphonia::default::get_probe()
+            .format(&Hint::new(), stream, &FormatOptions::default(), &MetadataOptions::default())
+            .with_context(|| format!("Failed to read audio file: {}", path.display()))?
+            .format;
+
+        let track = format.tracks().iter()
+            .find(|track| track.codec_params.codec == CODEC_TYPE_OPUS)
+            .ok_or_else(|| anyhow::anyhow!("No Opus stream in {}", path.display()))?;
+        let (channels, count) = match track.codec_params.channels.map(|c| c.count()) {
+            Some(1) => (Channels::Mono, 1),
+            Some(2) => (Channels::Stereo, 2),
+            _ => anyhow::bail!("Only mono and stereo Opus can be played: {}", path.display()),
+        };
+        // The decoder's first frames (the pre-skip, symphonia's delay) are the encoder
+        // warming up, and the last packet is padded; the length symphonia finds counts both
+        let track_id = track.id;
+        let delay = track.codec_params.delay.unwrap_or(0) as u64;
+        let padding = track.codec_params.padding.unwrap_or(0) as u64;
+        let origin = track.codec_params.start_ts + delay;
+        let frames = track.codec_params.n_frames.map(|frames| frames.saturating_sub(delay + padding));
+
+        let decoder = PacketDecoder::new(audiopus::SampleRate::Hz48000, channels)
+            .with_context(|| format!("Failed to decode audio file: {}", path.display()))?;
+
+        Ok(Self {
+            format,
+            track_id,
+            decoder,
+            channels: count,
+            origin,
+            frames,
+            buffer: Vec::new(),
+            index: 0,
+            start: 0,
+        })
+    }
+
+    /// Decode the next packet of the stream into the buffer; false at the end
+    fn decode_packet(&mut self) -> bool {
+        let channels = self.channels as usize;
+        loop {
+            // Any read error ends the stream, as in rodio's decoders
+            let packet = match self.format.next_packet() {
+                Ok(packet) if packet.track_id() == self.track_id => packet,
+                Ok(_) => continue,
+                Err(_) => return false,
+            };
+
+            self.buffer.resize(MAX_PACKET_FRAMES * channels, 0.0);
+            let decoded = Packet::try_from(packet.buf())
+                .and_then(|input| Ok((input, MutSignals::try_from(&mut self.buffer[..])?)))
+                .and_then(|(input, output)| self.decoder.decode_float(Some(input), output, false));
+            // A damaged packet is skipped rather than ending the song
+            let Ok(frames) = decoded else {
+                continue;
+            };
+
+            // Keep the frames from the start (or seek target) to the end of the stream,
+            // which drops the pre-skip and the padding of the last packet
+            let ts = packet.ts();
+            let start = (self.origin + self.start).saturating_sub(ts).min(frames as u64) as usize;
+            let end = match self.frames {
+                Some(length) => (self.origin + length).saturating_sub(ts).min(frames as u64) as usize,
+                None => frames,
+            };
+            self.buffer.truncate(end.max(start) * channels);
+            self.index = start * channels;
+            return true;
+        }
+    }
+}
+
+impl Iterator for OpusDecoder {
+    type Item = Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<Sample> {
+        while self.index >= self.buffer.len() {
+            if !self.decode_packet() {
+                return None;
+            }
+        }
+        let sample = self.buffer[self.index];
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl Source for OpusDecoder {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        OPUS_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.frames.map(|frames| Duration::from_secs_f64(frames as f64 / OPUS_RATE as f64))
+    }
+
+    /// Seek to the page before the target, then decode up to it
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let mut target = (pos.as_secs_f64() * OPUS_RATE as f64).round() as u64;
+        if let Some(frames) = self.frames {
+            target = target.min(frames);
+        }
+
+        self.format
+            .seek(SeekMode::Accurate, SeekTo::TimeStamp {
+                ts: self.origin + target.saturating_sub(SEEK_PREROLL),
+                track_id: self.track_id,
+            })
+            .map_err(|e| SeekError::Other(Box::new(e)))?;
+        self.decoder.reset_state().map_err(|e| SeekError::Other(Box::new(e)))?;
+
+        self.buffer.clear();
+        self.index = 0;
+        self.start = target;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiopus::coder::Encoder;
+    use audiopus::Application;
+
+    /// Ogg page checksum: CRC-32 with polynomial 0x04c11db7, unreflected
+    fn ogg_crc(data: &[u8]) -> u32 {
+        let mut crc = 0u32;
+        for &byte in data {
+            crc ^= (byte as u32) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+            }
+        }
+        crc
+    }
+
+    /// One Ogg page holding one packet
+    fn ogg_page(packet: &[u8], granule: u64, sequence: u32, header_type: u8) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(header_type);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes()); // Stream serial
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]); // Checksum, filled in below
+        let mut lacing = vec![255u8; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(packet);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// Stereo Ogg Opus file of `frames` frames at 48 kHz: a 440 Hz tone on the left
+    /// channel, silence on the right
+    fn write_opus(path: &Path, frames: usize) {
+        let encoder = Encoder::new(audiopus::SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        let pre_skip = encoder.lookahead().unwrap() as usize;
+
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&OPUS_RATE.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]); // Gain, channel mapping
+        let mut file = ogg_page(&head, 0, 0, 0x02);
+        file.extend(ogg_page(b"OpusTags\0\0\0\0\0\0\0\0", 0, 1, 0));
+
+        // 20 ms packets. The decoder's output runs pre-skip frames behind the input, so
+        // silence is fed until all of it is out; the last granule position cuts it there.
+        let mut samples: Vec<f32> = (0..frames)
+            .flat_map(|i| [(i as f32 * 440.0 * std::f32::consts::TAU / OPUS_RATE as f32).sin() * 0.5, 0.0])
+            .collect();
+        let packets = (frames + pre_skip).div_ceil(960);
+        samples.resize(packets * 960 * 2, 0.0);
+        for (index, input) in samples.chunks(960 * 2).enumerate() {
+            let mut packet = vec![0u8; 4000];
+            let len = encoder.encode_float(input, &mut packet).unwrap();
+            let last = index + 1 == packets;
+            let granule = if last { pre_skip + frames } else { (index + 1) * 960 };
+            file.extend(ogg_page(&packet[..len], granule as u64, index as u32 + 2, if last { 0x04 } else { 0 }));
+        }
+        std::fs::write(path, file).unwrap();
+    }
+
+    #[test]
+    fn test_decodes_and_seeks_ogg_opus() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-opus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lead.ogg");
+        write_opus(&path, 48_000 + 100);
+
+        // Opus in an .ogg is told apart from Vorbis by its header
+        let opened = super::super::open_decoder(&path).unwrap();
+        assert_eq!(opened.total_duration(), Some(Duration::from_secs_f64(48_100.0 / 48_000.0)));
+
+        let mut decoder = OpusDecoder::open(&path).unwrap();
+        assert_eq!(decoder.channels(), 2);
+        assert_eq!(decoder.sample_rate(), OPUS_RATE);
+        assert_eq!(decoder.total_duration(), Some(Duration::from_secs_f64(48_100.0 / 48_000.0)));
+
+        // Pre-skip and the padding of the last packet are trimmed
+        let samples: Vec<f32> = decoder.by_ref().collect();
+        assert_eq!(samples.len(), 48_100 * 2);
+        let left = samples.iter().step_by(2).map(|s| s.abs()).fold(0.0, f32::max);
+        let right = samples.iter().skip(1).step_by(2).map(|s| s.abs()).fold(0.0, f32::max);
+        assert!(left > 0.3 && right < 0.05, "left {} right {}", left, right);
+
+        // Seeking lands on the exact frame, with the decoder settled by then
+        decoder.try_seek(Duration::from_millis(500)).unwrap();
+        let after_seek: Vec<f32> = decoder.by_ref().collect();
+        assert_eq!(after_seek.len(), 24_100 * 2);
+        for (seeked, played) in after_seek.iter().zip(&samples[48_000..]).take(200) {
+            assert!((seeked - played).abs() < 0.05, "{} != {}", seeked, played);
+        }
+        decoder.try_seek(Duration::ZERO).unwrap();
+        assert_eq!(decoder.count(), 48_100 * 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
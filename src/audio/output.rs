@@ -0,0 +1,398 @@
+use anyhow::{Context, Result};
+use rodio::mixer::{Mixer, MixerSource};
+use rodio::{ChannelCount, DeviceTrait, OutputStream, OutputStreamBuilder, SampleRate, Source};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Sample rate of outputs without a sound device
+const VIRTUAL_SAMPLE_RATE: SampleRate = 44100;
+
+/// How much audio a virtual output consumes each time it wakes up
+const VIRTUAL_PERIOD: Duration = Duration::from_millis(10);
+
+/// Where the engine sends its audio. Written in the config and on the command
+/// line as `device`, `null` or `file:<path.wav>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum OutputBackend {
+    /// The system's sound device
+    #[default]
+    Device,
+    /// No sound at all: the mix is consumed in real time and discarded (headless machines, tests)
+    Null,
+    /// Everything played is recorded to a WAV file instead of being heard
+    File(PathBuf),
+}
+
+impl FromStr for OutputBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "device" => Ok(Self::Device),
+            "null" => Ok(Self::Null),
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(Self::File(PathBuf::from(path))),
+                _ => anyhow::bail!("Unknown audio output '{}' (expected device, null or file:<path.wav>)", s),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for OutputBackend {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for OutputBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Device => write!(f, "device"),
+            Self::Null => write!(f, "null"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+impl From<OutputBackend> for String {
+    fn from(backend: OutputBackend) -> Self {
+        backend.to_string()
+    }
+}
+
+/// An audio destination: the engine connects its sinks to the output's mixer
+pub trait AudioOutput: Send {
+    fn mixer(&self) -> &Mixer;
+
+    /// Number of output channels, e.g. 4 for an interface with two stereo pairs
+    fn channels(&self) -> ChannelCount;
+
+    /// Short description for logs and the UI
+    fn describe(&self) -> String;
+
+    /// Why the output stopped working, once it has (e.g. the device was unplugged)
+    fn failure(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether audio only moves on when the output is advanced by hand
+    /// (see `VirtualOutput::stepped`)
+    fn is_stepped(&self) -> bool {
+        false
+    }
+}
+
+/// Open the output for a backend; `device` names the sound device to use, if not the
+/// default, and `virtual_channels` is the channel count of the null and file outputs
+pub fn open(backend: &OutputBackend, device: Option<&str>, virtual_channels: ChannelCount) -> Result<Box<dyn AudioOutput>> {
+    Ok(match backend {
+        OutputBackend::Device => Box::new(DeviceOutput::open(device)?),
+        OutputBackend::Null => Box::new(VirtualOutput::null(virtual_channels)),
+        OutputBackend::File(path) => Box::new(VirtualOutput::file(path.clone(), virtual_channels)?),
+    })
+}
+
+/// Names of the sound devices audio can be played through
+pub fn output_devices() -> Vec<String> {
+    let host = rodio::cpal::default_host();
+    match rodio::cpal::traits::HostTrait::output_devices(&host) {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            eprintln!("Failed to list audio devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Plays through a sound device
+pub struct DeviceOutput {
+    stream: OutputStream,
+    name: Option<String>, // None for the system default
+    failure: Arc<Mutex<Option<String>>>, // Set from the stream's error callback
+}
+
+impl DeviceOutput {
+    /// Open the named device, falling back to the default device if it's
+    /// missing or can't be opened
+    pub fn open(name: Option<&str>) -> Result<Self> {
+        if let Some(name) = name {
+            match Self::open_named(name) {
+                Ok(output) => return Ok(output),
+                Err(e) => eprintln!("{:#}; using the default device", e),
+            }
+        }
+        Self::open_default()
+    }
+
+    pub fn open_default() -> Result<Self> {
+        let failure = Arc::new(Mutex::new(None));
+        let mut stream = OutputStreamBuilder::from_default_device()
+            .and_then(|builder| builder.with_error_callback(report_failure(&failure)).open_stream_or_fallback())
+            .context("Failed to create audio output stream")?;
+        stream.log_on_drop(false);
+        Ok(Self { stream, name: None, failure })
+    }
+
+    fn open_named(name: &str) -> Result<Self> {
+        let host = rodio::cpal::default_host();
+        let device = rodio::cpal::traits::HostTrait::output_devices(&host)?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .with_context(|| format!("Audio device '{}' not found", name))?;
+
+        let failure = Arc::new(Mutex::new(None));
+        let mut stream = OutputStreamBuilder::from_device(device)
+            .and_then(|builder| builder.with_error_callback(report_failure(&failure)).open_stream_or_fallback())
+            .with_context(|| format!("Failed to open audio device '{}'", name))?;
+        stream.log_on_drop(false);
+        Ok(Self { stream, name: Some(name.to_string()), failure })
+    }
+}
+
+/// Stream error callback recording the device going away; other stream errors
+/// (e.g. an underrun) are only logged
+fn report_failure(failure: &Arc<Mutex<Option<String>>>) -> impl FnMut(rodio::cpal::StreamError) + Clone + Send + 'static {
+    let failure = failure.clone();
+    move |e| match e {
+        rodio::cpal::StreamError::DeviceNotAvailable => {
+            *failure.lock().unwrap() = Some(e.to_string());
+        }
+        e => eprintln!("Audio stream error: {}", e),
+    }
+}
+
+impl AudioOutput for DeviceOutput {
+    fn mixer(&self) -> &Mixer {
+        self.stream.mixer()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.stream.config().channel_count()
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => "default device".to_string(),
+        }
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failure.lock().unwrap().clone()
+    }
+}
+
+/// An output with no sound device behind it. A thread stands in for the device,
+/// pulling the mix at real-time pace from a virtual clock (frames consumed, not
+/// wall time), and optionally recording it to a WAV file. A stepped output has
+/// no thread: audio is pulled only by `advance`, so tests run the same every time.
+pub struct VirtualOutput {
+    mixer: Mixer,
+    channels: ChannelCount,
+    path: Option<PathBuf>,
+    device: Arc<Mutex<VirtualDevice>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+type Recorder = hound::WavWriter<BufWriter<File>>;
+
+/// The mix as a virtual device sees it
+struct VirtualDevice {
+    source: MixerSource,
+    recorder: Option<Recorder>,
+}
+
+impl VirtualDevice {
+    /// Consume `frames` frames of the mix, recording them if recording
+    fn pull(&mut self, frames: u64) {
+        for _ in 0..frames * self.source.channels() as u64 {
+            let sample = self.source.next().unwrap_or(0.0);
+            if let Some(writer) = &mut self.recorder
+                && let Err(e) = writer.write_sample(super::mixdown::to_pcm16(sample))
+            {
+                eprintln!("Stopped recording audio output: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+}
+
+impl VirtualOutput {
+    /// Discard everything played
+    pub fn null(channels: ChannelCount) -> Self {
+        Self::start(channels, None, None, true)
+    }
+
+    /// Record everything played to a 16-bit WAV file, finished when the output is dropped
+    pub fn file(path: PathBuf, channels: ChannelCount) -> Result<Self> {
+        let recorder = Self::recorder(&path, channels)?;
+        Ok(Self::start(channels, Some(path), Some(recorder), true))
+    }
+
+    /// Discard everything played, pulling audio only when `advance`d
+    #[cfg(test)]
+    pub fn stepped(channels: ChannelCount) -> Self {
+        Self::start(channels, None, None, false)
+    }
+
+    /// Record to a WAV file like `file`, pulling audio only when `advance`d
+    #[cfg(test)]
+    pub fn stepped_file(path: PathBuf, channels: ChannelCount) -> Result<Self> {
+        let recorder = Self::recorder(&path, channels)?;
+        Ok(Self::start(channels, Some(path), Some(recorder), false))
+    }
+
+    /// Handle that advances a stepped output from wherever the output itself went
+    #[cfg(test)]
+    pub fn stepper(&self) -> VirtualStepper {
+        VirtualStepper { device: self.device.clone() }
+    }
+
+    fn recorder(path: &PathBuf, channels: ChannelCount) -> Result<Recorder> {
+        let spec = hound::WavSpec {
+            channels: channels.max(1),
+            sample_rate: VIRTUAL_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        hound::WavWriter::create(path, spec).with_context(|| format!("Failed to create {}", path.display()))
+    }
+
+    fn start(channels: ChannelCount, path: Option<PathBuf>, recorder: Option<Recorder>, realtime: bool) -> Self {
+        let channels = channels.max(1);
+        let (mixer, source) = rodio::mixer::mixer(channels, VIRTUAL_SAMPLE_RATE);
+        let device = Arc::new(Mutex::new(VirtualDevice { source, recorder }));
+        let running = Arc::new(AtomicBool::new(realtime));
+        let thread = realtime.then(|| {
+            let (device, running) = (device.clone(), running.clone());
+            std::thread::spawn(move || run_virtual_device(&device, &running))
+        });
+
+        Self {
+            mixer,
+            channels,
+            path,
+            device,
+            running,
+            thread,
+        }
+    }
+}
+
+fn run_virtual_device(device: &Mutex<VirtualDevice>, running: &AtomicBool) {
+    let period_frames = (VIRTUAL_PERIOD.as_secs_f64() * VIRTUAL_SAMPLE_RATE as f64) as u64;
+    let started = Instant::now();
+    let mut consumed = 0u64;
+
+    while running.load(Ordering::Relaxed) {
+        device.lock().unwrap().pull(period_frames);
+        consumed += period_frames;
+
+        // Sleep until the wall clock catches up with the audio consumed so far
+        let due = started + Duration::from_secs_f64(consumed as f64 / VIRTUAL_SAMPLE_RATE as f64);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Advances a stepped `VirtualOutput`, standing in for its device
+#[cfg(test)]
+#[derive(Clone)]
+pub struct VirtualStepper {
+    device: Arc<Mutex<VirtualDevice>>,
+}
+
+#[cfg(test)]
+impl VirtualStepper {
+    /// Pull exactly `frames` frames of the mix through the output
+    pub fn advance(&self, frames: u64) {
+        self.device.lock().unwrap().pull(frames);
+    }
+
+    /// Pull as many frames as play in `duration`
+    pub fn advance_by(&self, duration: Duration) {
+        self.advance((duration.as_secs_f64() * VIRTUAL_SAMPLE_RATE as f64).round() as u64);
+    }
+}
+
+impl AudioOutput for VirtualOutput {
+    fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    fn describe(&self) -> String {
+        match &self.path {
+            Some(path) => format!("recording {} channels to {}", self.channels, path.display()),
+            None => format!("null output ({} channels)", self.channels),
+        }
+    }
+
+    fn is_stepped(&self) -> bool {
+        self.thread.is_none()
+    }
+}
+
+impl Drop for VirtualOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        if let Some(writer) = self.device.lock().unwrap().recorder.take()
+            && let Err(e) = writer.finalize()
+        {
+            eprintln!("Failed to finish audio recording: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_round_trip() {
+        for text in ["device", "null", "file:out/session.wav"] {
+            let backend: OutputBackend = text.parse().unwrap();
+            assert_eq!(backend.to_string(), text);
+        }
+        assert!("speakers".parse::<OutputBackend>().is_err());
+        assert!("file:".parse::<OutputBackend>().is_err());
+    }
+
+    #[test]
+    fn test_file_output_records_what_plays() {
+        let path = std::env::temp_dir().join(format!("tanukioke-output-{}.wav", std::process::id()));
+        let output = VirtualOutput::stepped_file(path.clone(), 2).unwrap();
+        assert!(output.is_stepped());
+        output.mixer().add(rodio::buffer::SamplesBuffer::new(2, VIRTUAL_SAMPLE_RATE, vec![0.5; 882]));
+        output.stepper().advance(1000);
+        drop(output);
+
+        let samples: Vec<i16> = hound::WavReader::open(&path).unwrap()
+            .into_samples()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples.len(), 2000);
+        assert_eq!(samples.iter().filter(|s| **s == super::super::mixdown::to_pcm16(0.5)).count(), 882);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use anyhow::{Context, Result};
@@ -37,6 +38,79 @@ pub struct Config {
 
     #[serde(default = "default_timing_offset")]
     pub lyrics_timing_offset: f64,
+
+    // Audio settings
+    #[serde(default)]
+    pub audio_output: crate::audio::output::OutputBackend,
+
+    // Sound device to play through; the system default if unset or missing
+    #[serde(default)]
+    pub audio_device: Option<String>,
+
+    // Channel count of the null and file outputs (e.g. 4 to try routing to a second pair)
+    #[serde(default = "default_virtual_channels")]
+    pub virtual_channels: u16,
+
+    #[serde(default = "default_master_volume")]
+    pub master_volume: f32,
+
+    #[serde(default = "default_loudness_normalization")]
+    pub loudness_normalization: bool,
+
+    #[serde(default = "default_loudness_target")]
+    pub loudness_target_lufs: f64,
+
+    // Gain ramp on play, pause, stop and seek, so the audio never cuts with a click
+    #[serde(default = "default_transport_fade_ms")]
+    pub transport_fade_ms: u64,
+
+    // Output latency for syncing lyrics to what is heard; estimated from the output buffer if unset
+    #[serde(default)]
+    pub output_latency_ms: Option<u64>,
+
+    // Length of the host's "fade out" (stop gracefully) action
+    #[serde(default = "default_fade_out_seconds")]
+    pub fade_out_seconds: f32,
+
+    // Queue settings
+    // Play the next queued song automatically when one ends, after an "up next" screen
+    #[serde(default)]
+    pub auto_advance: bool,
+
+    #[serde(default = "default_interstitial_seconds")]
+    pub interstitial_seconds: f32,
+
+    // Music looped between songs while auto-advancing
+    #[serde(default)]
+    pub intermission_track: Option<String>,
+
+    // How long the end of a song and the intermission track overlap (0 = no crossfade)
+    #[serde(default = "default_crossfade_seconds")]
+    pub crossfade_seconds: f32,
+
+    // Click a count-in before each repetition of a practice loop
+    #[serde(default)]
+    pub count_in: bool,
+
+    // Beats in the count-in
+    #[serde(default = "default_count_in_beats")]
+    pub count_in_beats: u32,
+
+    // Count-in tempo for songs without a [bpm:] tag, in beats per minute
+    #[serde(default = "default_count_in_bpm")]
+    pub count_in_bpm: f64,
+
+    // Per-song mixer settings, keyed by LRX file path
+    #[serde(default)]
+    pub songs: BTreeMap<String, SongSettings>,
+}
+
+/// Mixer settings remembered for one song
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SongSettings {
+    /// Effects chain per LRX track id
+    #[serde(default)]
+    pub track_dsp: BTreeMap<String, crate::audio::dsp::DspSettings>,
 }
 
 fn default_opacity_current() -> f32 { 1.0 }
@@ -49,6 +123,16 @@ fn default_fg_color() -> String { "#FFFFFF".to_string() }
 fn default_bg_color() -> Option<String> { None }
 fn default_snappiness() -> f32 { 15.0 }
 fn default_timing_offset() -> f64 { 0.0 }
+fn default_virtual_channels() -> u16 { 2 }
+fn default_master_volume() -> f32 { 1.0 }
+fn default_loudness_normalization() -> bool { true }
+fn default_loudness_target() -> f64 { -16.0 }
+fn default_interstitial_seconds() -> f32 { 15.0 }
+fn default_transport_fade_ms() -> u64 { 40 }
+fn default_fade_out_seconds() -> f32 { 5.0 }
+fn default_crossfade_seconds() -> f32 { 4.0 }
+fn default_count_in_beats() -> u32 { 4 }
+fn default_count_in_bpm() -> f64 { 120.0 }
 
 impl Default for Config {
     fn default() -> Self {
@@ -64,6 +148,23 @@ impl Default for Config {
             lyrics_default_bg_color: default_bg_color(),
             lyrics_snappiness: default_snappiness(),
             lyrics_timing_offset: default_timing_offset(),
+            audio_output: Default::default(),
+            audio_device: None,
+            virtual_channels: default_virtual_channels(),
+            master_volume: default_master_volume(),
+            loudness_normalization: default_loudness_normalization(),
+            loudness_target_lufs: default_loudness_target(),
+            transport_fade_ms: default_transport_fade_ms(),
+            output_latency_ms: None,
+            fade_out_seconds: default_fade_out_seconds(),
+            auto_advance: false,
+            interstitial_seconds: default_interstitial_seconds(),
+            intermission_track: None,
+            crossfade_seconds: default_crossfade_seconds(),
+            count_in: false,
+            count_in_beats: default_count_in_beats(),
+            count_in_bpm: default_count_in_bpm(),
+            songs: BTreeMap::new(),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use walkdir::WalkDir;
 use anyhow::{Context, Result};
@@ -11,12 +11,74 @@ pub struct SongMetadata {
     pub title: String,
 }
 
+/// Size and modification time of a file, to tell when something cached about it is stale
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified: u64, // Nanoseconds since the Unix epoch
+}
+
+impl FileStamp {
+    /// Stamp of the file as it is now; None if it can't be read
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(Self { size: metadata.len(), modified: modified.as_nanos() as u64 })
+    }
+}
+
+/// Length of a stem found by scanning it, for the file as it was then
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StemDuration {
+    pub path: PathBuf,
+    pub stamp: FileStamp,
+    pub seconds: f64,
+}
+
+/// One stem as it was when a song's loudness was measured
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StemKey {
+    pub path: PathBuf,
+    pub volume: f32,
+    pub stamp: FileStamp,
+}
+
+/// Everything a loudness measurement depends on: the stems with their volumes
+/// and file stamps. The whole mix is measured, so trim points don't count. A
+/// measurement cached under another key is stale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessKey {
+    pub stems: Vec<StemKey>, // Sorted by path, as the LRX tracks come in no set order
+}
+
+impl LoudnessKey {
+    /// Key of a song as its files are now; None if a stem can't be read
+    pub fn for_song(lrx: &crate::lrx::LrxFile, song_dir: &Path) -> Option<Self> {
+        let mut stems = lrx.tracks
+            .values()
+            .map(|track| {
+                let path = song_dir.join(&track.source);
+                let stamp = FileStamp::of(&path)?;
+                Some(StemKey { path, volume: track.volume, stamp })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        stems.sort_by(|a, b| a.path.cmp(&b.path));
+        Some(Self { stems })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct RegistryEntry {
     artist: String,
     album: String,
     title: String,
     lrx_path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loudness: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loudness_key: Option<LoudnessKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stem_durations: Vec<StemDuration>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,6 +106,9 @@ pub struct Song {
     pub folder: PathBuf,
     pub tracks: Vec<Track>,
     pub lrx_path: Option<PathBuf>,
+    pub loudness: Option<f64>, // Integrated loudness of the mixed stems (LUFS), once analyzed
+    pub loudness_key: Option<LoudnessKey>, // What the loudness was measured from
+    pub stem_durations: Vec<StemDuration>, // Lengths of the stems the decoder couldn't tell, once scanned
     metadata_cache: Arc<Mutex<Option<SongMetadata>>>,
 }
 
@@ -53,10 +118,18 @@ impl Song {
             folder,
             tracks: Vec::new(),
             lrx_path: None,
+            loudness: None,
+            loudness_key: None,
+            stem_durations: Vec::new(),
             metadata_cache: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// The cached loudness and what it was measured from, if both are known
+    pub fn cached_loudness(&self) -> Option<(f64, LoudnessKey)> {
+        self.loudness.zip(self.loudness_key.clone())
+    }
+
     pub fn title(&self) -> String {
         self.folder
             .file_name()
@@ -77,8 +150,8 @@ impl Song {
 
         // Parse LRX file to extract metadata
         let metadata = if let Some(lrx_path) = &self.lrx_path {
-            if let Ok(content) = std::fs::read_to_string(lrx_path) {
-                if let Ok(lrx) = crate::lrx::LrxFile::parse(&content) {
+            if let Ok(decoded) = crate::lrx::encoding::read_to_string(lrx_path) {
+                if let Ok(lrx) = crate::lrx::LrxFile::parse(&decoded.content) {
                     SongMetadata {
                         artist: lrx.metadata.get("ar").cloned().unwrap_or_default(),
                         album: lrx.metadata.get("al").cloned().unwrap_or_default(),
@@ -147,14 +220,11 @@ pub fn scan_library(path: &str) -> Result<Vec<Song>> {
             }
 
             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                match ext {
-                    "lrx" => {
-                        song.lrx_path = Some(path.clone());
-                    }
-                    "mp3" | "flac" | "wav" | "ogg" | "opus" => {
-                        song.tracks.push(Track::new(path.clone()));
-                    }
-                    _ => {}
+                let ext = ext.to_ascii_lowercase();
+                if ext == "lrx" {
+                    song.lrx_path = Some(path.clone());
+                } else if crate::audio::AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+                    song.tracks.push(Track::new(path.clone()));
                 }
             }
         }
@@ -171,6 +241,22 @@ pub fn scan_library(path: &str) -> Result<Vec<Song>> {
     Ok(songs)
 }
 
+/// Carry what's cached about each song (loudness, scanned stem lengths) over from
+/// `previous`, matched by LRX path, so a rescan doesn't throw it away. Whatever has
+/// gone stale since is caught by its key and file stamps when it's used.
+pub fn keep_cached(songs: &mut [Song], previous: &[Song]) {
+    let previous: std::collections::HashMap<&PathBuf, &Song> = previous.iter()
+        .filter_map(|song| Some((song.lrx_path.as_ref()?, song)))
+        .collect();
+    for song in songs {
+        if let Some(old) = song.lrx_path.as_ref().and_then(|path| previous.get(path)) {
+            song.loudness = old.loudness;
+            song.loudness_key = old.loudness_key.clone();
+            song.stem_durations = old.stem_durations.clone();
+        }
+    }
+}
+
 /// Load library from registry file if it exists, otherwise scan and create registry
 pub fn load_or_scan_library(path: &str) -> Result<Vec<Song>> {
     let library_path = PathBuf::from(path);
@@ -213,6 +299,9 @@ pub fn save_registry(path: &PathBuf, songs: &[Song]) -> Result<()> {
                 album: metadata.album,
                 title: metadata.title,
                 lrx_path: lrx_path.clone(),
+                loudness: song.loudness,
+                loudness_key: song.loudness_key.clone(),
+                stem_durations: song.stem_durations.clone(),
             })
         })
         .collect();
@@ -233,6 +322,18 @@ pub fn save_registry(path: &PathBuf, songs: &[Song]) -> Result<()> {
     Ok(())
 }
 
+/// A song's loudness from the library registry, if it was measured from its files
+/// as `key` describes them now
+pub fn registry_loudness(library_path: &str, lrx_path: &Path, key: &LoudnessKey) -> Option<f64> {
+    let songs = load_registry(&PathBuf::from(library_path).join("library.toml")).ok()?;
+    let lrx_path = std::fs::canonicalize(lrx_path).ok()?;
+    songs.iter()
+        .find(|song| song.lrx_path.as_ref().and_then(|path| std::fs::canonicalize(path).ok()) == Some(lrx_path.clone()))
+        .and_then(|song| song.cached_loudness())
+        .filter(|(_, cached)| cached == key)
+        .map(|(loudness, _)| loudness)
+}
+
 /// Load library from registry file
 fn load_registry(path: &PathBuf) -> Result<Vec<Song>> {
     let content = std::fs::read_to_string(path)
@@ -251,6 +352,9 @@ fn load_registry(path: &PathBuf) -> Result<Vec<Song>> {
 
             let mut song = Song::new(folder);
             song.lrx_path = Some(entry.lrx_path);
+            song.loudness = entry.loudness;
+            song.loudness_key = entry.loudness_key;
+            song.stem_durations = entry.stem_durations;
 
             // Pre-populate metadata cache
             let metadata = SongMetadata {
@@ -276,4 +380,49 @@ mod tests {
         let song = Song::new(PathBuf::from("/path/to/My Song"));
         assert_eq!(song.title(), "My Song");
     }
+
+    #[test]
+    fn test_keep_cached_matches_by_lrx_path() {
+        let song = |name: &str| {
+            let mut song = Song::new(PathBuf::from(name));
+            song.lrx_path = Some(PathBuf::from(name).join("song.lrx"));
+            song
+        };
+        let mut old = song("/library/a");
+        old.loudness = Some(-12.0);
+        old.loudness_key = Some(LoudnessKey { stems: Vec::new() });
+        old.stem_durations = vec![StemDuration {
+            path: PathBuf::from("/library/a/lead.mp3"),
+            stamp: FileStamp { size: 1, modified: 2 },
+            seconds: 180.0,
+        }];
+
+        let mut songs = vec![song("/library/b"), song("/library/a")];
+        keep_cached(&mut songs, &[old.clone(), song("/library/gone")]);
+        assert_eq!(songs[0].loudness, None);
+        assert_eq!(songs[1].cached_loudness(), old.cached_loudness());
+        assert_eq!(songs[1].stem_durations, old.stem_durations);
+    }
+
+    #[test]
+    fn test_loudness_key_ignores_track_order() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-library-key-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut content = String::from("[ti:Test]\n");
+        for id in ["vocals", "drums", "bass", "keys"] {
+            std::fs::write(dir.join(format!("{}.wav", id)), id).unwrap();
+            content.push_str(&format!("[track.{}:source={}.wav]\n", id, id));
+        }
+
+        // Every parse fills the track map in another order
+        let key = LoudnessKey::for_song(&crate::lrx::LrxFile::parse(&content).unwrap(), &dir).unwrap();
+        let paths: Vec<_> = key.stems.iter().map(|stem| stem.path.file_name().unwrap().to_owned()).collect();
+        assert_eq!(paths, ["bass.wav", "drums.wav", "keys.wav", "vocals.wav"]);
+        for _ in 0..10 {
+            let lrx = crate::lrx::LrxFile::parse(&content).unwrap();
+            assert_eq!(LoudnessKey::for_song(&lrx, &dir).as_ref(), Some(&key));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,300 @@
+use crate::audio::OpenedTracks;
+use crate::library::{FileStamp, LoudnessKey, StemDuration};
+use crate::lrx::LrxFile;
+use crate::lrx::encoding::DecodedText;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread::JoinHandle;
+
+/// Share of the progress bar taken by opening the stems; loudness analysis, when
+/// the song needs it, takes the rest
+const OPEN_PROGRESS_SHARE: f32 = 0.2;
+
+/// What the library registry already knows about a song being prepared
+#[derive(Debug, Clone, PartialEq)]
+pub struct SongLookup {
+    pub normalize: bool, // False to skip loudness entirely
+    pub cached_loudness: Option<(f64, LoudnessKey)>, // Loudness measurement and what it was measured from
+    pub cached_durations: Vec<StemDuration>, // Stem lengths found by earlier scans
+}
+
+/// Everything read from disk for a song: its lyrics file and its stems, opened
+/// and ready to hand to the audio engine
+pub struct PreparedSong {
+    pub lrx_path: PathBuf,
+    pub song_dir: PathBuf,
+    pub decoded: DecodedText,
+    pub lrx: LrxFile,
+    pub tracks: OpenedTracks,
+    pub loudness: Option<f64>,
+    pub measured_loudness: Option<LoudnessKey>, // Loudness was analyzed now from these files, worth caching
+    pub scanned_durations: Vec<StemDuration>, // Stem lengths found by scanning now, worth caching
+}
+
+/// Read, parse and open a song, measuring its loudness and length if they aren't
+/// known yet. `progress` gets the fraction done.
+pub fn prepare_song(lrx_path: &Path, lookup: SongLookup, mut progress: impl FnMut(f32)) -> Result<PreparedSong> {
+    // Read (detecting the text encoding) and parse LRX file
+    let decoded = crate::lrx::encoding::read_to_string(lrx_path)
+        .with_context(|| format!("Failed to read LRX file: {}", lrx_path.display()))?;
+    let lrx = LrxFile::parse(&decoded.content)
+        .with_context(|| format!("Failed to parse LRX file: {}", lrx_path.display()))?;
+
+    // Relative audio paths are resolved against the LRX file's directory
+    let song_dir = lrx_path.parent()
+        .ok_or_else(|| anyhow::anyhow!("LRX file has no parent directory"))?
+        .to_path_buf();
+
+    let track_infos = lrx.tracks
+        .values()
+        .map(|track| (track.id.clone(), track.name.clone(), track.source.clone(), track.volume))
+        .collect();
+    let mut tracks = crate::audio::open_tracks(track_infos, Some(&song_dir), |fraction| {
+        progress(fraction * OPEN_PROGRESS_SHARE);
+    })
+    .context("Failed to load audio tracks")?;
+
+    // Lengths the decoders don't know come from the registry (if the file hasn't
+    // changed since), a scan of the file, or the [length:] tag, in that order
+    let seconds = |s: f64| std::time::Duration::from_secs_f64(s.max(0.0));
+    let cached = |path: &Path| {
+        let stamp = FileStamp::of(path)?;
+        lookup.cached_durations.iter()
+            .find(|cached| cached.path == path && cached.stamp == stamp)
+            .map(|cached| seconds(cached.seconds))
+    };
+    let scanned_durations = tracks.resolve_durations(cached, lrx.length().map(seconds))
+        .into_iter()
+        .filter_map(|(path, duration)| {
+            Some(StemDuration { stamp: FileStamp::of(&path)?, path, seconds: duration.as_secs_f64() })
+        })
+        .collect();
+
+    // Loudness from the [loudness:] override, the registry (if measured from the
+    // files as they are now), or measured now
+    let mut measured_loudness = None;
+    let key = lookup.normalize.then(|| LoudnessKey::for_song(&lrx, &song_dir)).flatten();
+    let cached = lookup.cached_loudness
+        .filter(|(_, cached_key)| key.as_ref() == Some(cached_key))
+        .map(|(loudness, _)| loudness);
+    let loudness = if !lookup.normalize {
+        None
+    } else if let Some(known) = lrx.loudness().or(cached) {
+        Some(known)
+    } else {
+        println!("Analyzing loudness of {}...", lrx_path.display());
+        let analyzed = crate::audio::loudness::analyze_lrx(&lrx, &song_dir, |fraction| {
+            progress(OPEN_PROGRESS_SHARE + fraction * (1.0 - OPEN_PROGRESS_SHARE));
+        });
+        match analyzed {
+            Ok(analyzed) => {
+                measured_loudness = key.filter(|_| analyzed.is_some());
+                analyzed
+            }
+            Err(e) => {
+                eprintln!("Loudness analysis failed: {:#}", e);
+                None
+            }
+        }
+    };
+
+    progress(1.0);
+    Ok(PreparedSong {
+        lrx_path: lrx_path.to_path_buf(),
+        song_dir,
+        decoded,
+        lrx,
+        tracks,
+        loudness,
+        measured_loudness,
+        scanned_durations,
+    })
+}
+
+/// A song being prepared on a background thread
+struct LoadJob {
+    lrx_path: PathBuf,
+    lookup: SongLookup,
+    progress: Arc<AtomicU32>, // f32 bits, 0.0..=1.0
+    handle: JoinHandle<Result<PreparedSong>>,
+}
+
+impl LoadJob {
+    fn spawn(lrx_path: PathBuf, lookup: SongLookup) -> Self {
+        let progress = Arc::new(AtomicU32::new(0));
+        let handle = std::thread::spawn({
+            let lrx_path = lrx_path.clone();
+            let progress = progress.clone();
+            let lookup = lookup.clone();
+            move || prepare_song(&lrx_path, lookup, |fraction| {
+                progress.store(fraction.to_bits(), Ordering::Relaxed);
+            })
+        });
+        Self { lrx_path, lookup, progress, handle }
+    }
+
+    fn is_for(&self, lrx_path: &Path, lookup: &SongLookup) -> bool {
+        self.lrx_path == lrx_path && self.lookup == *lookup
+    }
+}
+
+/// Prepares songs off the UI thread: the song asked for, and the one expected next,
+/// so that switching to it only takes handing its stems to the engine.
+///
+/// Jobs that are replaced are abandoned; their threads finish on their own and
+/// the result is dropped.
+#[derive(Default)]
+pub struct SongLoader {
+    loading: Option<LoadJob>,
+    preload: Option<LoadJob>,
+}
+
+impl SongLoader {
+    /// Start loading a song, taking over the preload if it's for the same song
+    pub fn load(&mut self, lrx_path: PathBuf, lookup: SongLookup) {
+        if self.loading.as_ref().is_some_and(|job| job.is_for(&lrx_path, &lookup)) {
+            return;
+        }
+        self.loading = match self.preload.take() {
+            Some(job) if job.is_for(&lrx_path, &lookup) => Some(job),
+            _ => Some(LoadJob::spawn(lrx_path, lookup)),
+        };
+    }
+
+    /// Prepare a song in the background in case it's loaded next
+    pub fn preload(&mut self, lrx_path: PathBuf, lookup: SongLookup) {
+        let already = |job: &Option<LoadJob>| job.as_ref().is_some_and(|job| job.is_for(&lrx_path, &lookup));
+        if already(&self.preload) || already(&self.loading) {
+            return;
+        }
+        println!("Preloading {}", lrx_path.display());
+        self.preload = Some(LoadJob::spawn(lrx_path, lookup));
+    }
+
+    /// Drop a preloaded song, e.g. because its files changed since
+    pub fn forget_preload(&mut self, lrx_path: &Path) {
+        if self.preload.as_ref().is_some_and(|job| job.lrx_path == lrx_path) {
+            self.preload = None;
+        }
+    }
+
+    /// The song being loaded and how far along it is
+    pub fn progress(&self) -> Option<(&Path, f32)> {
+        self.loading.as_ref().map(|job| {
+            (job.lrx_path.as_path(), f32::from_bits(job.progress.load(Ordering::Relaxed)))
+        })
+    }
+
+    /// The loaded song, once it's ready
+    pub fn poll(&mut self) -> Option<(PathBuf, Result<PreparedSong>)> {
+        if !self.loading.as_ref().is_some_and(|job| job.handle.is_finished()) {
+            return None;
+        }
+        let job = self.loading.take().unwrap();
+        let result = job.handle.join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Song loading thread panicked")));
+        Some((job.lrx_path, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTHING_KNOWN: SongLookup = SongLookup { normalize: false, cached_loudness: None, cached_durations: Vec::new() };
+
+    fn write_song(dir: &Path) -> PathBuf {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join("backing.wav"), spec).unwrap();
+        for _ in 0..8000 {
+            writer.write_sample(1000i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let lrx_path = dir.join("song.lrx");
+        std::fs::write(&lrx_path, "[ti:Test]\n[track.backing:name=Backing]\n[track.backing:source=backing.wav]\n").unwrap();
+        lrx_path
+    }
+
+    fn wait_for(loader: &mut SongLoader) -> (PathBuf, Result<PreparedSong>) {
+        loop {
+            if let Some(result) = loader.poll() {
+                return result;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_load_takes_over_preload() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lrx_path = write_song(&dir);
+
+        let mut loader = SongLoader::default();
+        loader.preload(lrx_path.clone(), NOTHING_KNOWN);
+        loader.load(lrx_path.clone(), NOTHING_KNOWN);
+        assert!(loader.preload.is_none());
+        assert_eq!(loader.progress().map(|(path, _)| path), Some(lrx_path.as_path()));
+
+        let (path, song) = wait_for(&mut loader);
+        let song = song.unwrap();
+        assert_eq!(path, lrx_path);
+        assert_eq!(song.lrx.tracks.len(), 1);
+        assert_eq!(song.song_dir, dir);
+        assert!(loader.progress().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_reports_missing_stems() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-loader-missing-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lrx_path = write_song(&dir);
+        std::fs::remove_file(dir.join("backing.wav")).unwrap();
+
+        let mut loader = SongLoader::default();
+        loader.load(lrx_path, NOTHING_KNOWN);
+        let (_, song) = wait_for(&mut loader);
+        assert!(song.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remeasures_stale_loudness() {
+        let dir = std::env::temp_dir().join(format!("tanukioke-loader-loudness-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lrx_path = write_song(&dir);
+        let lrx = LrxFile::parse(&std::fs::read_to_string(&lrx_path).unwrap()).unwrap();
+        let key = LoudnessKey::for_song(&lrx, &dir).unwrap();
+        let lookup = |key: LoudnessKey| SongLookup { normalize: true, cached_loudness: Some((-99.0, key)), cached_durations: Vec::new() };
+
+        // Cached from the same files: used as is
+        let song = prepare_song(&lrx_path, lookup(key.clone()), |_| {}).unwrap();
+        assert_eq!((song.loudness, song.measured_loudness), (Some(-99.0), None));
+
+        // Measured at another track volume: measured again
+        let mut stale = key.clone();
+        stale.stems[0].volume = 0.5;
+        let song = prepare_song(&lrx_path, lookup(stale), |_| {}).unwrap();
+        assert!(song.loudness.is_some_and(|loudness| loudness > -99.0));
+        assert_eq!(song.measured_loudness, Some(key.clone()));
+
+        // The stem file was replaced since
+        let mut stale = key;
+        stale.stems[0].stamp.size += 1;
+        let song = prepare_song(&lrx_path, lookup(stale), |_| {}).unwrap();
+        assert!(song.measured_loudness.is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
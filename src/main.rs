@@ -2,11 +2,12 @@ mod app;
 mod audio;
 mod config;
 mod library;
-mod lrx;
+mod loader;
 mod queue;
 mod ui;
 
 use eframe::egui;
+use tanukioke_lrx as lrx;
 
 fn main() -> anyhow::Result<()> {
     // Check for test mode
@@ -14,6 +15,31 @@ fn main() -> anyhow::Result<()> {
     if args.len() > 1 && args[1] == "test" {
         return test_library();
     }
+    if args.len() > 1 && args[1] == "check-timing" {
+        return check_timing(args.get(2).map(std::path::PathBuf::from));
+    }
+    if args.len() > 1 && args[1] == "analyze-loudness" {
+        return analyze_loudness(args.get(2).map(std::path::PathBuf::from));
+    }
+    if args.len() > 1 && args[1] == "mixdown" {
+        let lrx_path = args.get(2)
+            .map(std::path::PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("Usage: tanukioke mixdown <file.lrx> [output.wav]"))?;
+        return mixdown(lrx_path, args.get(3).map(std::path::PathBuf::from));
+    }
+    if args.len() > 1 && args[1] == "lint-fix" {
+        let dry_run = args[2..].iter().any(|a| a == "--dry-run");
+        let path = args[2..].iter().find(|a| !a.starts_with("--")).map(std::path::PathBuf::from);
+        return lint_fix(path, dry_run);
+    }
+
+    // `--audio <output>` overrides the configured audio output for this run
+    let audio_output = match args.iter().position(|a| a == "--audio") {
+        Some(i) => Some(args.get(i + 1)
+            .ok_or_else(|| anyhow::anyhow!("--audio needs an output: device, null or file:<path.wav>"))?
+            .parse::<audio::output::OutputBackend>()?),
+        None => None,
+    };
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -25,13 +51,12 @@ fn main() -> anyhow::Result<()> {
     eframe::run_native(
         "Tanukioke",
         options,
-        Box::new(|_cc| Ok(Box::new(app::App::new()))),
+        Box::new(|_cc| Ok(Box::new(app::App::new(audio_output)))),
     )
     .map_err(|e| anyhow::anyhow!("Failed to run app: {}", e))
 }
 
 fn test_library() -> anyhow::Result<()> {
-    use std::fs;
     use lrx::LrxFile;
 
     println!("=== Tanukioke Library Test ===\n");
@@ -63,9 +88,13 @@ fn test_library() -> anyhow::Result<()> {
         // Try to parse the LRX file
         if let Some(lrx_path) = &song.lrx_path {
             println!("  Parsing LRX file...");
-            match fs::read_to_string(lrx_path) {
-                Ok(content) => {
-                    match LrxFile::parse(&content) {
+            match lrx::encoding::read_to_string(lrx_path) {
+                Ok(decoded) => {
+                    if !decoded.is_utf8() {
+                        println!("    ! Encoding: {} (not UTF-8)", decoded.encoding_name());
+                    }
+
+                    match LrxFile::parse(&decoded.content) {
                         Ok(lrx) => {
                             println!("    ✓ Parsed successfully");
 
@@ -119,3 +148,227 @@ fn test_library() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Print a timing-quality report for one LRX file, or for the whole library if no path is given
+fn check_timing(path: Option<std::path::PathBuf>) -> anyhow::Result<()> {
+    use lrx::LrxFile;
+    use lrx::analyze::TimingOptions;
+
+    let lrx_paths = lrx_paths_or_library(path)?;
+    let options = TimingOptions::default();
+    let mut total_warnings = 0;
+
+    for lrx_path in &lrx_paths {
+        let lrx = match lrx::encoding::read_to_string(lrx_path)
+            .and_then(|decoded| LrxFile::parse(&decoded.content))
+        {
+            Ok(lrx) => lrx,
+            Err(e) => {
+                println!("{}\n  ✗ {:#}\n", lrx_path.display(), e);
+                continue;
+            }
+        };
+
+        // Longest audio track is the song length; the analyzer falls back to [length:]
+        let song_dir = lrx_path.parent().unwrap_or(std::path::Path::new("."));
+        let audio_duration = lrx.tracks
+            .values()
+            .filter_map(|track| audio::probe_duration(&song_dir.join(&track.source)).ok())
+            .max()
+            .map(|d| d.as_secs_f64());
+
+        let issues = lrx.analyze_timing(audio_duration, &options);
+        if issues.is_empty() {
+            continue;
+        }
+
+        println!("{}", lrx_path.display());
+        for issue in &issues {
+            let line = &lrx.lines[issue.line_index];
+            let minutes = (line.timestamp / 60.0).floor() as i32;
+            let secs = line.timestamp - minutes as f64 * 60.0;
+            println!("  [{:02}:{:05.2}] {} - \"{}\"", minutes, secs, issue.message(), line.text);
+        }
+        println!();
+        total_warnings += issues.len();
+    }
+
+    println!("{} timing warning(s) in {} file(s)", total_warnings, lrx_paths.len());
+    Ok(())
+}
+
+/// Normalize LRX files in place (one file, or the whole library if no path is given)
+fn lint_fix(path: Option<std::path::PathBuf>, dry_run: bool) -> anyhow::Result<()> {
+    let lrx_paths = lrx_paths_or_library(path)?;
+    let mut fixed_count = 0;
+
+    for lrx_path in &lrx_paths {
+        let decoded = match lrx::encoding::read_to_string(lrx_path) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                println!("{}\n  ✗ {:#}\n", lrx_path.display(), e);
+                continue;
+            }
+        };
+
+        let result = match lrx::fix::fix_content(&decoded.content) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("{}\n  ✗ {:#}\n", lrx_path.display(), e);
+                continue;
+            }
+        };
+
+        if !result.changed(&decoded.content) && decoded.is_utf8() {
+            continue;
+        }
+
+        println!("{}", lrx_path.display());
+        for fix in &result.fixes {
+            println!("  - {}", fix);
+        }
+        if !decoded.is_utf8() {
+            println!("  - Converted from {} to UTF-8", decoded.encoding_name());
+        }
+
+        // Comments aren't part of the LRX model, so a rewrite would lose them
+        if result.dropped_lines > 0 {
+            println!("  ! Skipped: {} comment/unrecognized line(s) would be lost (use the editor's Auto-fix)\n", result.dropped_lines);
+            continue;
+        }
+
+        if !dry_run {
+            std::fs::write(lrx_path, &result.content)?;
+        }
+        fixed_count += 1;
+        println!();
+    }
+
+    let verb = if dry_run { "would be fixed" } else { "fixed" };
+    println!("{} of {} file(s) {}", fixed_count, lrx_paths.len(), verb);
+    Ok(())
+}
+
+/// Measure the loudness of one song, or of every library song not yet analyzed
+/// (caching the results in the library registry)
+fn analyze_loudness(path: Option<std::path::PathBuf>) -> anyhow::Result<()> {
+    use lrx::LrxFile;
+
+    let config = config::Config::load()?;
+    let target = config.loudness_target_lufs;
+
+    // Loudness of a song, unless `cached` was measured from the files as they are now
+    let measure = |lrx_path: &std::path::Path, cached: Option<(f64, library::LoudnessKey)>|
+        -> anyhow::Result<(Option<f64>, Option<library::LoudnessKey>)>
+    {
+        let lrx = LrxFile::parse(&lrx::encoding::read_to_string(lrx_path)?.content)?;
+        let song_dir = lrx_path.parent().unwrap_or(std::path::Path::new("."));
+        let key = library::LoudnessKey::for_song(&lrx, song_dir);
+        if let Some((loudness, cached_key)) = cached
+            && key.as_ref() == Some(&cached_key)
+        {
+            return Ok((Some(loudness), key));
+        }
+        Ok((audio::loudness::analyze_lrx(&lrx, song_dir, |_| {})?, key))
+    };
+    let report = |lrx_path: &std::path::Path, loudness: Option<f64>| match loudness {
+        Some(loudness) => println!(
+            "{:>6.1} LUFS  {:+5.1} dB  {}",
+            loudness, audio::loudness::normalization_gain_db(loudness, target), lrx_path.display()
+        ),
+        None => println!("     silent           {}", lrx_path.display()),
+    };
+
+    if let Some(path) = path {
+        report(&path, measure(&path, None)?.0);
+        return Ok(());
+    }
+
+    let library_path = config.library_path
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No library path configured"))?;
+    let mut songs = library::load_or_scan_library(library_path)?;
+
+    println!("Target {:.1} LUFS\n", target);
+    for song in &mut songs {
+        let Some(lrx_path) = song.lrx_path.clone() else { continue };
+        match measure(&lrx_path, song.cached_loudness()) {
+            Ok((loudness, key)) => {
+                song.loudness = loudness;
+                song.loudness_key = key;
+            }
+            Err(e) => {
+                println!("  ✗ {}: {:#}", lrx_path.display(), e);
+                continue;
+            }
+        }
+        report(&lrx_path, song.loudness);
+    }
+
+    library::save_registry(&std::path::PathBuf::from(library_path).join("library.toml"), &songs)
+}
+
+/// Render a song to a WAV file with its LRX track volumes, saved effects and
+/// loudness normalization, as the player would play it with nothing muted
+fn mixdown(lrx_path: std::path::PathBuf, output: Option<std::path::PathBuf>) -> anyhow::Result<()> {
+    use lrx::LrxFile;
+
+    let config = config::Config::load()?;
+    let lrx = LrxFile::parse(&lrx::encoding::read_to_string(&lrx_path)?.content)?;
+    let song_dir = lrx_path.parent().unwrap_or(std::path::Path::new("."));
+
+    let mut mixdown = audio::mixdown::Mixdown::from_lrx(&lrx, song_dir);
+    mixdown.master_volume = config.master_volume;
+    if let Some(settings) = config.songs.get(lrx_path.to_string_lossy().as_ref()) {
+        for track in &mut mixdown.tracks {
+            if let Some(dsp) = settings.track_dsp.get(&track.id) {
+                track.dsp = dsp.clone();
+            }
+        }
+    }
+    if config.loudness_normalization {
+        // The [loudness:] override, the registry's measurement, or measured now
+        let cached = || {
+            let key = library::LoudnessKey::for_song(&lrx, song_dir)?;
+            library::registry_loudness(config.library_path.as_deref()?, &lrx_path, &key)
+        };
+        let loudness = match lrx.loudness().or_else(cached) {
+            Some(loudness) => Some(loudness),
+            None => audio::loudness::analyze_lrx(&lrx, song_dir, |_| {})?,
+        };
+        if let Some(loudness) = loudness {
+            mixdown.gain_db = audio::loudness::normalization_gain_db(loudness, config.loudness_target_lufs) as f32;
+        }
+    }
+
+    let output = output.unwrap_or_else(|| audio::mixdown::default_output_path(&lrx_path));
+    println!("Rendering {} to {}", lrx_path.display(), output.display());
+    let mut last_percent = 0;
+    let length = mixdown.render_to_wav(&output, |fraction| {
+        let percent = (fraction * 100.0) as i32;
+        if percent >= last_percent + 10 {
+            println!("  {}%", percent);
+            last_percent = percent;
+        }
+    })?;
+    println!("Wrote {:.1}s of audio", length.as_secs_f64());
+    Ok(())
+}
+
+/// The given LRX file, or every LRX file in the configured library
+fn lrx_paths_or_library(path: Option<std::path::PathBuf>) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    if let Some(path) = path {
+        return Ok(vec![path]);
+    }
+
+    let config = config::Config::load()?;
+    let library_path = config.library_path
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No library path configured"))?;
+
+    Ok(library::scan_library(library_path)?
+        .into_iter()
+        .filter_map(|song| song.lrx_path)
+        .collect())
+}
+
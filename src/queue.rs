@@ -7,6 +7,7 @@ pub struct QueueEntry {
     pub song_title: String,
     pub lrx_path: Option<PathBuf>,
     pub url: Option<String>,
+    pub done: bool, // Already sung
 }
 
 impl QueueEntry {
@@ -23,6 +24,7 @@ impl QueueEntry {
             song_title,
             lrx_path,
             url,
+            done: false,
         }
     }
 }
@@ -140,6 +142,27 @@ impl Queue {
         self.current_index.and_then(|idx| self.entries.get(idx))
     }
 
+    /// Make the entry with the given ID the current one
+    pub fn set_current(&mut self, id: usize) {
+        if let Some(pos) = self.entries.iter().position(|e| e.id == id) {
+            self.current_index = Some(pos);
+        }
+    }
+
+    /// The next entry after the current one that has a library song to play
+    /// and hasn't been sung yet
+    pub fn upcoming(&self) -> Option<&QueueEntry> {
+        let from = self.current_index.map_or(0, |idx| idx + 1);
+        self.entries.iter().skip(from).find(|e| e.lrx_path.is_some() && !e.done)
+    }
+
+    /// Mark the current entry as sung
+    pub fn mark_current_done(&mut self) {
+        if let Some(idx) = self.current_index {
+            self.entries[idx].done = true;
+        }
+    }
+
     /// Move to the next entry in the queue
     pub fn next(&mut self) -> Option<&QueueEntry> {
         if let Some(idx) = self.current_index {
@@ -171,3 +194,30 @@ impl Default for Queue {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upcoming_skips_sung_and_url_entries() {
+        let mut queue = Queue::new();
+        let first = queue.add("Ann".into(), "One".into(), Some("one.lrx".into()), None);
+        queue.add("Bob".into(), "Video".into(), None, Some("https://example.com".into()));
+        let third = queue.add("Cat".into(), "Three".into(), Some("three.lrx".into()), None);
+        let fourth = queue.add("Dan".into(), "Four".into(), Some("four.lrx".into()), None);
+
+        // Nothing sung yet: the first library entry is up
+        assert_eq!(queue.upcoming().map(|e| e.id), Some(first));
+
+        queue.set_current(first);
+        queue.mark_current_done();
+        assert_eq!(queue.upcoming().map(|e| e.id), Some(third));
+
+        queue.get_mut(third).unwrap().done = true;
+        assert_eq!(queue.upcoming().map(|e| e.id), Some(fourth));
+
+        queue.set_current(fourth);
+        assert!(queue.upcoming().is_none());
+    }
+}
@@ -0,0 +1,32 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+/// Prompt shown after loading a lyric file that isn't plain UTF-8
+pub struct ResaveUtf8Dialog {
+    pub path: PathBuf,
+    pub encoding_name: String,
+    pub content: String,
+}
+
+/// Render the re-save prompt. Returns Some(true) to convert, Some(false) to keep as is.
+pub fn render(ctx: &egui::Context, dialog: &ResaveUtf8Dialog) -> Option<bool> {
+    let mut result = None;
+
+    egui::Window::new("Convert to UTF-8?")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("{} is encoded as {}.", dialog.path.display(), dialog.encoding_name));
+            ui.label("Re-save it as UTF-8 so other tools can read it?");
+            ui.horizontal(|ui| {
+                if ui.button("Re-save as UTF-8").clicked() {
+                    result = Some(true);
+                }
+                if ui.button("Keep As Is").clicked() {
+                    result = Some(false);
+                }
+            });
+        });
+
+    result
+}
@@ -10,6 +10,14 @@ pub struct EditorState {
     pub show_save_confirm: bool,
     pub show_help: bool,
     pub text_edit_id: egui::Id,
+    /// Name of the encoding the file was decoded from, if it wasn't plain UTF-8
+    pub source_encoding: Option<String>,
+    /// Timing warnings as (0-based line number in the text, message)
+    pub timing_warnings: Vec<(usize, String)>,
+    analyzed_content: Option<String>,
+    song_duration: Option<f64>, // Length of the audio, when the file being edited is the loaded song
+    /// Summary of the last auto-fix run
+    pub fix_message: Option<String>,
 }
 
 impl EditorState {
@@ -22,16 +30,27 @@ impl EditorState {
             show_save_confirm: false,
             show_help: false,
             text_edit_id: egui::Id::new("lrx_editor_text"),
+            source_encoding: None,
+            timing_warnings: Vec::new(),
+            analyzed_content: None,
+            song_duration: None,
+            fix_message: None,
         }
     }
 
-    pub fn load(&mut self, path: PathBuf, content: String) {
+    pub fn load(&mut self, path: PathBuf, decoded: crate::lrx::encoding::DecodedText) {
+        self.source_encoding = if decoded.is_utf8() {
+            None
+        } else {
+            Some(decoded.encoding_name())
+        };
         self.file_path = Some(path);
-        self.original_content = content.clone();
-        self.current_content = content;
+        self.original_content = decoded.content.clone();
+        self.current_content = decoded.content;
         self.show_close_confirm = false;
         self.show_save_confirm = false;
         self.show_help = false;
+        self.fix_message = None;
     }
 
     pub fn is_dirty(&self) -> bool {
@@ -45,6 +64,72 @@ impl EditorState {
         self.show_close_confirm = false;
         self.show_save_confirm = false;
         self.show_help = false;
+        self.source_encoding = None;
+        self.timing_warnings.clear();
+        self.analyzed_content = None;
+        self.fix_message = None;
+    }
+
+    /// Normalize the content in place (sort, trim, de-duplicate, canonical layout).
+    /// The result is left unsaved so it can be reviewed first.
+    pub fn auto_fix(&mut self) {
+        match crate::lrx::fix::fix_content(&self.current_content) {
+            Ok(result) => {
+                let mut summary = if result.changed(&self.current_content) {
+                    result.fixes.join("; ")
+                } else {
+                    "Nothing to fix".to_string()
+                };
+                if result.dropped_lines > 0 {
+                    summary.push_str(&format!("; removed {} comment/unrecognized line(s)", result.dropped_lines));
+                }
+
+                self.current_content = result.content;
+                self.fix_message = Some(summary);
+            }
+            Err(e) => {
+                self.fix_message = Some(format!("Can't auto-fix: {:#}", e));
+            }
+        }
+    }
+
+    /// Length of the song's audio, to check lines against; without it the
+    /// `[length:]` tag is used
+    pub fn set_song_duration(&mut self, duration: Option<f64>) {
+        if self.song_duration != duration {
+            self.song_duration = duration;
+            self.analyzed_content = None;
+        }
+    }
+
+    /// Re-run timing analysis if the content or song length changed since the last run
+    pub fn refresh_timing_warnings(&mut self) {
+        if self.analyzed_content.as_deref() == Some(self.current_content.as_str()) {
+            return;
+        }
+
+        self.timing_warnings = timing_warnings(&self.current_content, self.song_duration);
+        self.analyzed_content = Some(self.current_content.clone());
+    }
+
+    /// Move the editor cursor to the start of the given (0-based) text line
+    fn jump_to_line(&self, ctx: &egui::Context, line_number: usize) {
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, self.text_edit_id) {
+            let char_pos: usize = self.current_content
+                .split_inclusive('\n')
+                .take(line_number)
+                .map(|line| line.chars().count())
+                .sum();
+            let ccursor = egui::text::CCursor::new(char_pos);
+            state.cursor.set_char_range(Some(egui::text::CCursorRange::one(ccursor)));
+            state.store(ctx, self.text_edit_id);
+            ctx.memory_mut(|mem| mem.request_focus(self.text_edit_id));
+        }
+    }
+
+    /// Whether saving would change the file on disk (edits, or a pending UTF-8 conversion)
+    pub fn needs_save(&self) -> bool {
+        self.is_dirty() || self.source_encoding.is_some()
     }
 
     pub fn insert_timestamp(&mut self, ui: &mut egui::Ui, timestamp_seconds: f64) {
@@ -127,6 +212,38 @@ fn format_timestamp(seconds: f64) -> String {
     format!("[{:02}:{:02}.{:02}]", minutes, secs, centiseconds)
 }
 
+/// Run timing analysis on editor content and map each issue back to its line in the text
+fn timing_warnings(content: &str, song_duration: Option<f64>) -> Vec<(usize, String)> {
+    use crate::lrx::LrxFile;
+    use crate::lrx::analyze::TimingOptions;
+
+    let lrx = match LrxFile::parse(content) {
+        Ok(lrx) => lrx,
+        Err(_) => return Vec::new(),
+    };
+
+    // Text line numbers of lyric lines, in source order
+    let mut lyric_lines: Vec<(usize, f64)> = content
+        .lines()
+        .enumerate()
+        .filter_map(|(n, line)| {
+            let single = LrxFile::parse(line).ok()?;
+            single.lines.first().map(|lyric| (n, lyric.timestamp))
+        })
+        .collect();
+
+    // The parser stably sorts by timestamp; do the same so indices line up
+    lyric_lines.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    lrx.analyze_timing(song_duration, &TimingOptions::default())
+        .into_iter()
+        .filter_map(|issue| {
+            let (line_number, _) = lyric_lines.get(issue.line_index)?;
+            Some((*line_number, issue.message()))
+        })
+        .collect()
+}
+
 pub enum EditorAction {
     Save(PathBuf, String),
     Close,
@@ -151,8 +268,8 @@ pub fn render(ui: &mut egui::Ui, state: &mut EditorState, playback_position: Opt
                 }
             }
 
-            // Save button - enabled only if dirty
-            if ui.add_enabled(state.is_dirty(), egui::Button::new("💾 Save")).clicked() {
+            // Save button - enabled only if dirty or not yet UTF-8
+            if ui.add_enabled(state.needs_save(), egui::Button::new("💾 Save")).clicked() {
                 state.show_save_confirm = true;
             }
 
@@ -160,19 +277,63 @@ pub fn render(ui: &mut egui::Ui, state: &mut EditorState, playback_position: Opt
                 ui.label("*");
             }
 
+            if let Some(encoding) = &state.source_encoding {
+                ui.label(
+                    egui::RichText::new(format!("⚠ {}", encoding))
+                        .color(egui::Color32::from_rgb(255, 200, 80))
+                ).on_hover_text("This file is not plain UTF-8. Saving will convert it to UTF-8.");
+            }
+
             ui.separator();
 
             // Help button
             if ui.button("❓ Help").clicked() {
                 state.show_help = true;
             }
+
+            if ui.button("🧹 Auto-fix")
+                .on_hover_text("Sort lines, trim whitespace, remove duplicates, define missing parts and reorder sections")
+                .clicked()
+            {
+                state.auto_fix();
+            }
         });
     });
 
+    if let Some(message) = &state.fix_message {
+        ui.label(egui::RichText::new(message).italics());
+    }
+
     ui.separator();
 
+    state.refresh_timing_warnings();
+    let warning_lines: std::collections::HashSet<usize> = state.timing_warnings
+        .iter()
+        .map(|(line_number, _)| *line_number)
+        .collect();
+
+    // Highlight lines with timing warnings
+    let mut layouter = |ui: &egui::Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let color = ui.visuals().text_color();
+        let mut job = egui::text::LayoutJob::default();
+
+        for (n, line) in buf.as_str().split_inclusive('\n').enumerate() {
+            let mut format = egui::TextFormat::simple(font_id.clone(), color);
+            if warning_lines.contains(&n) {
+                format.background = egui::Color32::from_rgba_unmultiplied(200, 140, 0, 60);
+            }
+            job.append(line, 0.0, format);
+        }
+
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|f| f.layout_job(job))
+    };
+
     // Main text editor
-    let available_height = ui.available_height() - 70.0; // Reserve space for current lyric display and timestamp button
+    // Reserve space for timing warnings, current lyric display and timestamp button
+    let warnings_height = if state.timing_warnings.is_empty() { 0.0 } else { 110.0 };
+    let available_height = ui.available_height() - 70.0 - warnings_height;
     egui::ScrollArea::vertical()
         .auto_shrink([false, false])
         .max_height(available_height)
@@ -182,11 +343,39 @@ pub fn render(ui: &mut egui::Ui, state: &mut EditorState, playback_position: Opt
                     .id(state.text_edit_id)
                     .desired_width(f32::INFINITY)
                     .font(egui::TextStyle::Monospace)
+                    .layouter(&mut layouter)
             );
         });
 
     ui.separator();
 
+    // Timing warnings list - click to jump to the line
+    if !state.timing_warnings.is_empty() {
+        let mut jump_to = None;
+
+        ui.label(
+            egui::RichText::new(format!("⚠ {} timing warning(s)", state.timing_warnings.len()))
+                .color(egui::Color32::from_rgb(255, 200, 80))
+        );
+        egui::ScrollArea::vertical()
+            .id_salt("lrx_editor_timing_warnings")
+            .auto_shrink([false, true])
+            .max_height(80.0)
+            .show(ui, |ui| {
+                for (line_number, message) in &state.timing_warnings {
+                    if ui.link(format!("Line {}: {}", line_number + 1, message)).clicked() {
+                        jump_to = Some(*line_number);
+                    }
+                }
+            });
+
+        if let Some(line_number) = jump_to {
+            state.jump_to_line(ui.ctx(), line_number);
+        }
+
+        ui.separator();
+    }
+
     // Show current lyric preview (without timestamp)
     if let Some(text_state) = egui::TextEdit::load_state(ui.ctx(), state.text_edit_id) {
         if let Some(cursor_range) = text_state.cursor.char_range() {
@@ -290,7 +479,11 @@ pub fn render(ui: &mut egui::Ui, state: &mut EditorState, playback_position: Opt
             .collapsible(false)
             .resizable(false)
             .show(ui.ctx(), |ui| {
-                ui.label("Save changes to file?");
+                if let Some(encoding) = &state.source_encoding {
+                    ui.label(format!("Save changes to file? It will be converted from {} to UTF-8.", encoding));
+                } else {
+                    ui.label("Save changes to file?");
+                }
                 ui.horizontal(|ui| {
                     if ui.button("Save").clicked() {
                         if let Some(path) = &state.file_path {
@@ -16,11 +16,12 @@ pub fn render(ctx: &egui::Context, show: &mut bool) {
                     ui.separator();
 
                     ui.heading("File Structure");
-                    ui.label("An LRX file consists of four sections:");
+                    ui.label("An LRX file consists of these sections:");
                     ui.label("  1. Metadata Tags - Song information");
                     ui.label("  2. Track Definitions - Audio file references");
                     ui.label("  3. Part Definitions - Vocal part styling");
-                    ui.label("  4. Timed Lyrics - Timestamped lyric lines");
+                    ui.label("  4. Song Sections - Verse/chorus markers (optional)");
+                    ui.label("  5. Timed Lyrics - Timestamped lyric lines");
                     ui.add_space(10.0);
 
                     ui.separator();
@@ -57,16 +58,36 @@ pub fn render(ctx: &egui::Context, show: &mut bool) {
                             ui.label("[length:03:45]");
                             ui.end_row();
 
+                            ui.label("start");
+                            ui.label("Playback start point");
+                            ui.label("[start:00:04.50]");
+                            ui.end_row();
+
+                            ui.label("end");
+                            ui.label("Playback end point (fades out)");
+                            ui.label("[end:03:38.00]");
+                            ui.end_row();
+
                             ui.label("key");
                             ui.label("Musical key");
                             ui.label("[key:C] or [key:G#]");
                             ui.end_row();
 
+                            ui.label("bpm");
+                            ui.label("Tempo, for the practice loop count-in");
+                            ui.label("[bpm:96]");
+                            ui.end_row();
+
                             ui.label("offset");
                             ui.label("Global timing offset (ms)");
                             ui.label("[offset:+100]");
                             ui.end_row();
 
+                            ui.label("loudness");
+                            ui.label("Loudness override (LUFS)");
+                            ui.label("[loudness:-11.5]");
+                            ui.end_row();
+
                             ui.label("color");
                             ui.label("Global foreground color");
                             ui.label("[color:#FFFFFF]");
@@ -87,7 +108,7 @@ pub fn render(ctx: &egui::Context, show: &mut bool) {
                     ui.label("Example:");
                     ui.code("[track.instrumental:name=Instrumental]\n[track.instrumental:source=instrumental.mp3]\n[track.instrumental:volume=0.8]");
                     ui.add_space(5.0);
-                    ui.label("Properties: name (required), source (required), volume (0.0-1.0, default 1.0)");
+                    ui.label("Properties: name (required), source (required), volume (0.0-1.0, default 1.0), role (instrumental, vocal, backing, guide, click)");
                     ui.add_space(10.0);
 
                     ui.separator();
@@ -103,6 +124,17 @@ pub fn render(ctx: &egui::Context, show: &mut bool) {
 
                     ui.separator();
 
+                    ui.heading("Song Sections");
+                    ui.label("Sections mark verses, choruses etc. for practice loops: [section.{id}:{property}={value}]");
+                    ui.add_space(5.0);
+                    ui.label("Example:");
+                    ui.code("[section.verse1:name=Verse 1]\n[section.verse1:start=00:12.00]\n\n[section.chorus1:name=Chorus]\n[section.chorus1:start=00:45.50]");
+                    ui.add_space(5.0);
+                    ui.label("Properties: name (required), start (mm:ss.xx); a section runs until the next one starts");
+                    ui.add_space(10.0);
+
+                    ui.separator();
+
                     ui.heading("Timed Lyrics");
                     ui.label("Format: [mm:ss.xx][part]Lyric text");
                     ui.add_space(5.0);
@@ -4,12 +4,21 @@ use crate::app::PlaybackState;
 use crate::config::Config;
 use std::sync::{Arc, Mutex};
 
+/// The singer and song coming up next, shown between songs
+#[derive(Debug, Clone)]
+pub struct UpNext {
+    pub singer_name: String,
+    pub song_title: String,
+    pub remaining: Option<f64>, // Seconds until it starts, None while the host holds it
+}
+
 pub struct LyricsWindow {
     playback_state: Arc<Mutex<PlaybackState>>,
     lyrics: Option<LrxFile>,
     config: Config,
     // Store measured heights for each lyric line
     line_heights: Vec<f32>,
+    up_next: Option<UpNext>, // Shown instead of the lyrics while set
 }
 
 impl LyricsWindow {
@@ -19,14 +28,29 @@ impl LyricsWindow {
             lyrics,
             config,
             line_heights: Vec::new(),
+            up_next: None,
         }
     }
 
+    pub fn lyrics(&self) -> Option<&LrxFile> {
+        self.lyrics.as_ref()
+    }
+
     pub fn update_config(&mut self, config: Config) {
         self.config = config;
     }
 
+    pub fn set_up_next(&mut self, up_next: Option<UpNext>) {
+        self.up_next = up_next;
+    }
+
     pub fn render(&mut self, ctx: &egui::Context, window_height: f32) -> bool {
+        if let Some(up_next) = &self.up_next {
+            self.render_up_next(ctx, up_next, window_height);
+            ctx.request_repaint();
+            return ctx.input(|i| i.viewport().close_requested());
+        }
+
         let state = self.playback_state.lock().unwrap();
         let current_position = state.position + self.config.lyrics_timing_offset;
         let duration = state.duration;
@@ -41,6 +65,7 @@ impl LyricsWindow {
         // Get global background color
         let bg_color = if let Some(lyrics) = &self.lyrics {
             lyrics.background_color
+                .map(to_color32)
                 .or_else(|| {
                     self.config.lyrics_default_bg_color.as_ref()
                         .and_then(|s| Self::parse_hex_color(s))
@@ -88,7 +113,7 @@ impl LyricsWindow {
                             // Color fallback hierarchy: part > lrx global > config default
                             let fg_color = if let Some(part_id) = &line.part_id {
                                 if let Some(part) = lyrics.get_part(part_id) {
-                                    part.color
+                                    to_color32(part.color)
                                 } else {
                                     // Part doesn't exist, fall back to global/config
                                     self.get_default_color(lyrics)
@@ -168,6 +193,36 @@ impl LyricsWindow {
         ctx.input(|i| i.viewport().close_requested())
     }
 
+    /// Between songs: the next singer's name and song, with a countdown
+    fn render_up_next(&self, ctx: &egui::Context, up_next: &UpNext, window_height: f32) {
+        let fg_color = self.config.lyrics_default_fg_color.as_str();
+        let fg_color = Self::parse_hex_color(fg_color).unwrap_or(egui::Color32::WHITE);
+        let font_size = self.config.lyrics_font_size;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(window_height / 4.0);
+                ui.label(
+                    egui::RichText::new("Up next")
+                        .size(font_size * 0.6)
+                        .color(fg_color.linear_multiply(self.config.lyrics_opacity_upcoming))
+                );
+                ui.add_space(font_size * 0.5);
+                ui.label(egui::RichText::new(&up_next.singer_name).size(font_size * 1.5).strong().color(fg_color));
+                ui.label(egui::RichText::new(&up_next.song_title).size(font_size).color(fg_color));
+                ui.add_space(font_size);
+
+                if let Some(remaining) = up_next.remaining {
+                    ui.label(
+                        egui::RichText::new(format!("Starting in {}", remaining.ceil() as u32))
+                            .size(font_size * 0.6)
+                            .color(fg_color.linear_multiply(self.config.lyrics_opacity_upcoming))
+                    );
+                }
+            });
+        });
+    }
+
     fn find_current_line_index(&self, current_position: f64) -> Option<usize> {
         if let Some(lyrics) = &self.lyrics {
             for (i, line) in lyrics.lines.iter().enumerate().rev() {
@@ -271,6 +326,7 @@ impl LyricsWindow {
     /// Get default foreground color with fallback: lrx global > config default
     fn get_default_color(&self, lyrics: &LrxFile) -> egui::Color32 {
         lyrics.color
+            .map(to_color32)
             .or_else(|| Self::parse_hex_color(&self.config.lyrics_default_fg_color))
             .unwrap_or(egui::Color32::WHITE)
     }
@@ -288,3 +344,7 @@ impl LyricsWindow {
         Some(egui::Color32::from_rgb(r, g, b))
     }
 }
+
+fn to_color32(color: crate::lrx::Color) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a)
+}
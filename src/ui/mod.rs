@@ -1,7 +1,9 @@
+pub mod encoding_dialog;
 pub mod library_view;
 pub mod lrx_editor;
 pub mod lrx_help;
 pub mod lyrics_window;
 pub mod player;
+pub mod practice;
 pub mod queue;
 pub mod settings;
@@ -3,6 +3,13 @@ use std::sync::{Arc, Mutex};
 
 pub enum PlayerAction {
     OpenSettings,
+    MasterVolumeChanged(f32),
+    /// Render the current mix to a WAV file
+    Mixdown,
+    /// Fade the song out over the configured time, then stop
+    FadeOut,
+    /// A track's effects changed (track id, new settings); remembered for the song
+    TrackDspChanged(String, crate::audio::dsp::DspSettings),
 }
 
 pub fn render(
@@ -50,6 +57,13 @@ pub fn render(
                         engine.stop();
                     }
 
+                    if ui.add_sized([60.0, 35.0], egui::Button::new("🔉"))
+                        .on_hover_text("Fade out and stop")
+                        .clicked()
+                    {
+                        action = Some(PlayerAction::FadeOut);
+                    }
+
                     ui.add_space(10.0);
 
                     // Settings button
@@ -57,10 +71,20 @@ pub fn render(
                         action = Some(PlayerAction::OpenSettings);
                     }
 
+                    if ui.add_sized([60.0, 35.0], egui::Button::new("⤓"))
+                        .on_hover_text("Render the current mix to a WAV file next to the song")
+                        .clicked()
+                    {
+                        action = Some(PlayerAction::Mixdown);
+                    }
+
                     ui.add_space(10.0);
 
                     // Status indicator
-                    let status = if is_playing && !is_paused {
+                    let fading_out = audio_engine.lock().unwrap().is_fading_out();
+                    let status = if fading_out {
+                        "🔉 Fading out"
+                    } else if is_playing && !is_paused {
                         "⏵ Playing"
                     } else if is_paused {
                         "⏸ Paused"
@@ -69,6 +93,36 @@ pub fn render(
                     };
                     ui.label(status);
                 });
+
+                // Master bus: volume, level meter and clip indicator
+                ui.horizontal(|ui| {
+                    let engine = audio_engine.lock().unwrap();
+
+                    let mut volume = engine.master_volume();
+                    if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)
+                        .text("Master")
+                        .fixed_decimals(2))
+                        .changed()
+                    {
+                        engine.set_master_volume(volume);
+                        action = Some(PlayerAction::MasterVolumeChanged(volume));
+                    }
+
+                    let levels = engine.master_levels();
+                    let clipping = engine.is_clipping();
+                    level_meter(ui, levels, 100.0);
+                    ui.monospace(format_db(levels.peak));
+
+                    let clip_color = if clipping { egui::Color32::from_rgb(220, 60, 60) } else { ui.visuals().weak_text_color() };
+                    ui.colored_label(clip_color, "CLIP")
+                        .on_hover_text("Lit when the limiter had to stop the mix from clipping");
+
+                    let gain = engine.loudness_gain_db();
+                    if gain != 0.0 {
+                        ui.weak(format!("{:+.1} dB", gain))
+                            .on_hover_text("Loudness normalization gain for this song");
+                    }
+                });
             });
         });
 
@@ -81,13 +135,41 @@ pub fn render(
                     egui::ScrollArea::vertical()
                         .show(ui, |ui| {
                             let mut engine = audio_engine.lock().unwrap();
+                            let guide_vocal = engine.guide_vocal_index();
+
+                            // Toggle the guide vocal from the keyboard, unless typing somewhere
+                            if guide_vocal.is_some()
+                                && !ui.ctx().wants_keyboard_input()
+                                && ui.input(|i| i.key_pressed(egui::Key::V))
+                            {
+                                engine.toggle_guide_vocal();
+                            }
+
+                            let (mut set_muted, mut set_soloed) = (None, None);
+                            let output_pairs = engine.output_pairs();
                             let tracks = engine.tracks_mut();
 
                             if tracks.is_empty() {
                                 ui.label("No tracks loaded");
                             } else {
-                                for track in tracks {
+                                for (index, track) in tracks.iter_mut().enumerate() {
                                     ui.horizontal(|ui| {
+                                        let mute = ui.selectable_label(track.is_muted(), "M");
+                                        let mute = if guide_vocal == Some(index) {
+                                            mute.on_hover_text("Mute (V)")
+                                        } else {
+                                            mute.on_hover_text("Mute")
+                                        };
+                                        if mute.clicked() {
+                                            set_muted = Some((index, !track.is_muted()));
+                                        }
+                                        if ui.selectable_label(track.is_soloed(), "S")
+                                            .on_hover_text("Solo")
+                                            .clicked()
+                                        {
+                                            set_soloed = Some((index, !track.is_soloed()));
+                                        }
+
                                         ui.label(&track.name);
 
                                         let mut volume = track.get_volume();
@@ -100,9 +182,47 @@ pub fn render(
                                         }
 
                                         ui.label(format!("{}%", (volume * 100.0) as i32));
+
+                                        // Output pair, on interfaces with more than two channels
+                                        if output_pairs > 1 {
+                                            let mut pair = track.output_pair();
+                                            egui::ComboBox::from_id_salt(("track_output", &track.id))
+                                                .width(60.0)
+                                                .selected_text(output_pair_label(pair))
+                                                .show_ui(ui, |ui| {
+                                                    for p in 0..output_pairs {
+                                                        ui.selectable_value(&mut pair, p, output_pair_label(p));
+                                                    }
+                                                })
+                                                .response
+                                                .on_hover_text("Output channels");
+                                            if pair != track.output_pair() {
+                                                track.set_output_pair(pair);
+                                            }
+                                        }
+
+                                        level_meter(ui, track.levels(), 50.0);
                                     });
+
+                                    let mut dsp = track.dsp();
+                                    let title = if dsp.is_bypassed() { "Effects" } else { "Effects (on)" };
+                                    egui::CollapsingHeader::new(title)
+                                        .id_salt(("track_fx", &track.id))
+                                        .show(ui, |ui| {
+                                            if effects_controls(ui, &mut dsp) {
+                                                track.set_dsp(dsp.clone());
+                                                action = Some(PlayerAction::TrackDspChanged(track.id.clone(), dsp));
+                                            }
+                                        });
                                 }
                             }
+
+                            if let Some((index, muted)) = set_muted {
+                                engine.set_track_muted(index, muted);
+                            }
+                            if let Some((index, soloed)) = set_soloed {
+                                engine.set_track_soloed(index, soloed);
+                            }
                         });
                 }
             );
@@ -115,20 +235,34 @@ pub fn render(
     ui.horizontal(|ui| {
         let state = playback_state.lock().unwrap();
         let position = state.position;
-        let duration = state.duration;
+        let (start, end) = (state.start, state.end);
 
         ui.label(format_time(position));
 
+        // Slider covers the trimmed window ([start:] to [end:])
         let mut pos_f32 = position as f32;
-        let max = if duration > 0.0 { duration as f32 } else { 300.0 };
+        let min = start as f32;
+        let max = if end > start { end as f32 } else { min + 300.0 };
 
         // Try to make slider fill available space
         ui.style_mut().spacing.slider_width = ui.available_width() - 55.0;
 
-        let slider = egui::Slider::new(&mut pos_f32, 0.0..=max)
+        let slider = egui::Slider::new(&mut pos_f32, min..=max)
             .show_value(false);
+        let response = ui.add(slider);
 
-        if ui.add(slider).changed() {
+        // Shade the A–B loop
+        if let Some((loop_start, loop_end)) = audio_engine.lock().unwrap().loop_region() {
+            let rail = response.rect.shrink2(egui::vec2(response.rect.height() / 2.0, 0.0));
+            let x = |seconds: f64| rail.left() + rail.width() * ((seconds as f32 - min) / (max - min)).clamp(0.0, 1.0);
+            let region = egui::Rect::from_x_y_ranges(
+                x(loop_start.as_secs_f64())..=x(loop_end.as_secs_f64()),
+                response.rect.y_range(),
+            );
+            ui.painter().rect_filled(region, 2.0, egui::Color32::from_rgba_unmultiplied(90, 160, 255, 60));
+        }
+
+        if response.changed() {
             drop(state);
             let mut engine = audio_engine.lock().unwrap();
             let _ = engine.seek(std::time::Duration::from_secs_f64(pos_f32 as f64));
@@ -139,14 +273,123 @@ pub fn render(
         // Push the end timestamp to the right edge
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
             let state = playback_state.lock().unwrap();
-            ui.label(format_time(state.duration));
+            ui.label(format_time(state.end));
         });
     });
 
     action
 }
 
-fn format_time(seconds: f64) -> String {
+/// Channel numbers of an output pair, e.g. "3-4"
+fn output_pair_label(pair: u16) -> String {
+    format!("{}-{}", pair * 2 + 1, pair * 2 + 2)
+}
+
+/// Controls for one track's effects chain; true if anything changed
+fn effects_controls(ui: &mut egui::Ui, dsp: &mut crate::audio::dsp::DspSettings) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut dsp.vocal_reduction, "Vocal reduction")
+            .on_hover_text("Turn down what's panned center, for songs with only a full mix")
+            .changed();
+        ui.add_enabled_ui(dsp.vocal_reduction, |ui| {
+            changed |= ui.add(egui::Slider::new(&mut dsp.vocal_reduction_amount, 0.0..=1.0).text("Strength").fixed_decimals(2))
+                .changed();
+            changed |= ui.add(
+                egui::Slider::new(&mut dsp.vocal_crossover_hz, 40.0..=400.0)
+                    .logarithmic(true)
+                    .text("Keep bass")
+                    .suffix(" Hz"),
+            )
+                .on_hover_text("Centered bass and kick below this are kept")
+                .changed();
+        });
+    });
+
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut dsp.high_pass, "Low cut").changed();
+        changed |= ui.add_enabled(
+            dsp.high_pass,
+            egui::Slider::new(&mut dsp.high_pass_hz, 20.0..=400.0).logarithmic(true).suffix(" Hz"),
+        ).changed();
+    });
+
+    ui.horizontal(|ui| {
+        for (label, gain) in [
+            ("Low", &mut dsp.eq_low_db),
+            ("Mid", &mut dsp.eq_mid_db),
+            ("High", &mut dsp.eq_high_db),
+        ] {
+            changed |= ui.add(egui::Slider::new(gain, -12.0..=12.0).text(label).suffix(" dB").fixed_decimals(1))
+                .changed();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut dsp.compressor, "Compressor").changed();
+        ui.add_enabled_ui(dsp.compressor, |ui| {
+            changed |= ui.add(egui::Slider::new(&mut dsp.comp_threshold_db, -40.0..=0.0).text("Threshold").suffix(" dB"))
+                .changed();
+            changed |= ui.add(egui::Slider::new(&mut dsp.comp_ratio, 1.0..=10.0).text("Ratio").suffix(":1"))
+                .changed();
+            changed |= ui.add(egui::Slider::new(&mut dsp.comp_makeup_db, 0.0..=12.0).text("Makeup").suffix(" dB"))
+                .changed();
+        });
+    });
+
+    if ui.button("Reset").clicked() {
+        *dsp = Default::default();
+        changed = true;
+    }
+
+    changed
+}
+
+/// Lowest level shown on meters, in dBFS
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Horizontal level meter: RMS as a filled bar, peak as a tick, on a dB scale
+fn level_meter(ui: &mut egui::Ui, levels: crate::audio::meter::Levels, width: f32) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 10.0), egui::Sense::hover());
+    let painter = ui.painter();
+
+    let fraction = |level: f32| {
+        if level <= 0.0 {
+            return 0.0;
+        }
+        ((20.0 * level.log10() - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0)
+    };
+    let color = if levels.peak >= crate::audio::master::LIMITER_CEILING {
+        egui::Color32::from_rgb(220, 60, 60)
+    } else if levels.peak > 0.7 {
+        egui::Color32::from_rgb(220, 180, 60)
+    } else {
+        egui::Color32::from_rgb(80, 180, 90)
+    };
+
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let mut bar = rect;
+    bar.set_width(rect.width() * fraction(levels.rms));
+    painter.rect_filled(bar, 2.0, color);
+
+    let peak_x = rect.left() + rect.width() * fraction(levels.peak);
+    if levels.peak > 0.0 {
+        painter.vline(peak_x, rect.y_range(), egui::Stroke::new(2.0, color));
+    }
+
+    response.on_hover_text(format!("Peak {}  RMS {}", format_db(levels.peak), format_db(levels.rms)));
+}
+
+fn format_db(level: f32) -> String {
+    if level <= 0.0 {
+        return "-inf dB".to_string();
+    }
+    format!("{:.1} dB", 20.0 * level.log10())
+}
+
+pub fn format_time(seconds: f64) -> String {
     let minutes = (seconds / 60.0).floor() as i32;
     let secs = (seconds % 60.0).floor() as i32;
     format!("{:02}:{:02}", minutes, secs)
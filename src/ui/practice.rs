@@ -0,0 +1,159 @@
+use super::player::format_time;
+use crate::lrx::LrxFile;
+use eframe::egui;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub enum PracticeAction {
+    /// The count-in before each loop was switched on or off
+    CountInToggled(bool),
+}
+
+/// Loop points picked so far in the practice controls
+#[derive(Default)]
+pub struct PracticeState {
+    a: Option<f64>,             // Loop start set from the playhead, waiting for B
+    first_line: Option<usize>,  // Lyric line range, as indices into LrxFile::lines
+    last_line: Option<usize>,
+}
+
+impl PracticeState {
+    /// Forget the picked points, e.g. when another song loads
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A–B loop controls for rehearsals: loop points from the playhead, a song
+/// section or a range of lyric lines, and the count-in switch
+pub fn render(
+    ui: &mut egui::Ui,
+    audio_engine: &Arc<Mutex<crate::audio::AudioEngine>>,
+    playback_state: &Arc<Mutex<crate::app::PlaybackState>>,
+    lyrics: Option<&LrxFile>,
+    practice: &mut PracticeState,
+    count_in: bool,
+) -> Option<PracticeAction> {
+    let mut action = None;
+    let (position, end) = {
+        let state = playback_state.lock().unwrap();
+        (state.position, state.end)
+    };
+    let mut engine = audio_engine.lock().unwrap();
+    let mut new_loop = None;
+
+    ui.horizontal(|ui| {
+        ui.label("🔁 Loop:");
+
+        if ui.button("A").on_hover_text("Start the loop at the playhead").clicked() {
+            practice.a = Some(position);
+            engine.set_loop(None);
+        }
+        if ui.add_enabled(practice.a.is_some(), egui::Button::new("B"))
+            .on_hover_text("End the loop at the playhead and go back to A")
+            .clicked()
+            && let Some(a) = practice.a
+        {
+            new_loop = Some((a.min(position), a.max(position)));
+        }
+
+        match (engine.loop_region(), practice.a) {
+            (Some((start, end)), _) => {
+                ui.monospace(format!("{} – {}", format_time(start.as_secs_f64()), format_time(end.as_secs_f64())));
+                if ui.button("✖").on_hover_text("Stop looping").clicked() {
+                    engine.set_loop(None);
+                    practice.a = None;
+                }
+            }
+            (None, Some(a)) => {
+                ui.monospace(format!("{} – ?", format_time(a)));
+            }
+            (None, None) => {
+                ui.weak("Off");
+            }
+        }
+
+        let Some(lyrics) = lyrics else {
+            return;
+        };
+
+        // Song sections from the LRX file
+        let sections = lyrics.sections_in_order();
+        if !sections.is_empty() {
+            ui.separator();
+            egui::ComboBox::from_id_salt("loop_section")
+                .selected_text("Section")
+                .show_ui(ui, |ui| {
+                    for section in sections {
+                        if ui.selectable_label(false, &section.name).clicked()
+                            && let Some((start, section_end)) = lyrics.section_range(&section.id)
+                        {
+                            new_loop = Some((start, section_end.unwrap_or(end)));
+                        }
+                    }
+                });
+        }
+
+        // A range of lyric lines, up to the line after the last one picked
+        if !lyrics.lines.is_empty() {
+            ui.separator();
+            ui.label("Lines");
+            line_picker(ui, "loop_first_line", lyrics, &mut practice.first_line);
+            ui.label("to");
+            line_picker(ui, "loop_last_line", lyrics, &mut practice.last_line);
+
+            let range = practice.first_line.zip(practice.last_line)
+                .filter(|&(first, last)| first.max(last) < lyrics.lines.len());
+            if ui.add_enabled(range.is_some(), egui::Button::new("Loop lines")).clicked()
+                && let Some((first, last)) = range
+            {
+                let (first, last) = (first.min(last), first.max(last));
+                let start = lyrics.lines[first].timestamp;
+                let line_end = lyrics.lines[last + 1..].iter()
+                    .map(|line| line.timestamp)
+                    .find(|&timestamp| timestamp > lyrics.lines[last].timestamp)
+                    .unwrap_or(end);
+                new_loop = Some((start, line_end));
+            }
+        }
+
+        ui.separator();
+        let mut checked = count_in;
+        if ui.checkbox(&mut checked, "Count-in")
+            .on_hover_text("Click a count-in before each repetition")
+            .changed()
+        {
+            action = Some(PracticeAction::CountInToggled(checked));
+        }
+    });
+
+    // Start practicing the new loop from its beginning
+    if let Some((start, end)) = new_loop {
+        let (start, end) = (Duration::from_secs_f64(start.max(0.0)), Duration::from_secs_f64(end.max(0.0)));
+        engine.set_loop(Some((start, end)));
+        if let Some((start, _)) = engine.loop_region() {
+            practice.a = None;
+            let _ = engine.seek(start);
+        }
+    }
+
+    action
+}
+
+/// Combo box for picking one lyric line
+fn line_picker(ui: &mut egui::Ui, id: &str, lyrics: &LrxFile, selected: &mut Option<usize>) {
+    let label = |index: usize| {
+        let line = &lyrics.lines[index];
+        let text: String = line.text.chars().take(24).collect();
+        format!("{} {}", format_time(line.timestamp), text)
+    };
+
+    egui::ComboBox::from_id_salt(id)
+        .width(160.0)
+        .selected_text(selected.filter(|&i| i < lyrics.lines.len()).map(label).unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for index in 0..lyrics.lines.len() {
+                ui.selectable_value(selected, Some(index), label(index));
+            }
+        });
+}
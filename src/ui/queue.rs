@@ -1,10 +1,11 @@
 use eframe::egui;
 use crate::queue::Queue;
+use crate::ui::lyrics_window::UpNext;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum QueueAction {
-    Load(PathBuf),
+    Load(usize),
     Edit(usize),
     Delete(usize),
     MoveUp(usize),
@@ -14,6 +15,15 @@ pub enum QueueAction {
     AddManual,
 }
 
+/// Host controls for the "up next" screen between songs
+#[derive(Debug, Clone, Copy)]
+pub enum UpNextAction {
+    StartNow,
+    Hold,
+    Resume,
+    Cancel,
+}
+
 // Dialog state structs
 #[derive(Default)]
 pub struct AddManualDialog {
@@ -85,15 +95,20 @@ pub fn render(ui: &mut egui::Ui, queue: &Queue, is_playing: bool) -> Option<Queu
                             // Entry info section
                             ui.spacing_mut().item_spacing.y = 2.0;
 
-                            // Singer name (bold)
-                            ui.label(
+                            // Singer name (bold), ticked off once sung
+                            let singer = if entry.done {
+                                egui::RichText::new(format!("✔ {}", entry.singer_name)).weak()
+                            } else {
                                 egui::RichText::new(&entry.singer_name)
-                                    .strong()
-                                    .size(14.0)
-                            );
+                            };
+                            ui.label(singer.strong().size(14.0));
 
                             // Song title
-                            ui.label(&entry.song_title);
+                            if entry.done {
+                                ui.weak(&entry.song_title);
+                            } else {
+                                ui.label(&entry.song_title);
+                            }
 
                             // URL indicator
                             if let Some(url) = &entry.url {
@@ -137,7 +152,7 @@ pub fn render(ui: &mut egui::Ui, queue: &Queue, is_playing: bool) -> Option<Queu
                                     }
 
                                     // Load button (only if there's an LRX path)
-                                    if let Some(lrx_path) = &entry.lrx_path {
+                                    if entry.lrx_path.is_some() {
                                         let load_button = egui::Button::new("▶ Load");
                                         let load_response = if is_playing {
                                             ui.add_enabled(false, load_button)
@@ -146,7 +161,7 @@ pub fn render(ui: &mut egui::Ui, queue: &Queue, is_playing: bool) -> Option<Queu
                                         };
 
                                         if load_response.clicked() {
-                                            action = Some(QueueAction::Load(lrx_path.clone()));
+                                            action = Some(QueueAction::Load(entry.id));
                                         }
                                     }
                                 });
@@ -162,6 +177,36 @@ pub fn render(ui: &mut egui::Ui, queue: &Queue, is_playing: bool) -> Option<Queu
     action
 }
 
+/// Render the countdown to the next queued song, with controls to start it
+/// right away, hold it, or cancel auto-advance for now
+pub fn render_up_next(ui: &mut egui::Ui, up_next: &UpNext) -> Option<UpNextAction> {
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        let status = match up_next.remaining {
+            Some(remaining) => format!("in {}s", remaining.ceil() as u32),
+            None => "on hold".to_string(),
+        };
+        ui.label(format!("Up next: {} – {} ({})", up_next.singer_name, up_next.song_title, status));
+
+        if ui.button("▶ Start now").clicked() {
+            action = Some(UpNextAction::StartNow);
+        }
+        if up_next.remaining.is_some() {
+            if ui.button("⏸ Hold").clicked() {
+                action = Some(UpNextAction::Hold);
+            }
+        } else if ui.button("⏵ Resume countdown").clicked() {
+            action = Some(UpNextAction::Resume);
+        }
+        if ui.button("✖").on_hover_text("Don't advance").clicked() {
+            action = Some(UpNextAction::Cancel);
+        }
+    });
+
+    action
+}
+
 /// Render the add manual entry dialog
 pub fn render_add_manual_dialog(
     ctx: &egui::Context,
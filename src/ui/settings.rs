@@ -5,6 +5,7 @@ pub fn render(
     ui: &mut egui::Ui,
     config: &mut crate::config::Config,
     playback_state: &Arc<Mutex<crate::app::PlaybackState>>,
+    audio_devices: &mut Option<Vec<String>>,
 ) -> bool {
     let mut config_changed = false;
 
@@ -64,6 +65,103 @@ pub fn render(
 
         ui.add_space(10.0);
 
+        // Audio Section
+        ui.group(|ui| {
+            ui.label(egui::RichText::new("Audio").strong());
+            ui.add_space(5.0);
+
+            ui.add_enabled_ui(config.audio_output == crate::audio::output::OutputBackend::Device, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Output Device:");
+                    let devices = audio_devices.get_or_insert_with(crate::audio::output::output_devices);
+                    let selected = config.audio_device.as_deref().unwrap_or("System default").to_string();
+                    egui::ComboBox::from_id_salt("audio_device")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            if ui.selectable_label(config.audio_device.is_none(), "System default").clicked()
+                                && config.audio_device.is_some()
+                            {
+                                config.audio_device = None;
+                                config_changed = true;
+                            }
+                            for device in devices.iter() {
+                                let is_selected = config.audio_device.as_ref() == Some(device);
+                                if ui.selectable_label(is_selected, device).clicked() && !is_selected {
+                                    config.audio_device = Some(device.clone());
+                                    config_changed = true;
+                                }
+                            }
+                        });
+                    if ui.button("⟳").on_hover_text("Look for devices again").clicked() {
+                        *audio_devices = None;
+                    }
+                });
+            })
+                .response
+                .on_disabled_hover_text(format!("Audio output is set to '{}' in the config", config.audio_output));
+
+            ui.horizontal(|ui| {
+                ui.label("Transport Fade:");
+                if ui.add(egui::Slider::new(&mut config.transport_fade_ms, 0..=500).suffix(" ms"))
+                    .on_hover_text("Volume ramp on play, pause, stop and seek")
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Output Latency:");
+                let mut manual = config.output_latency_ms.is_some();
+                if ui.checkbox(&mut manual, "Manual")
+                    .on_hover_text("Estimated from the output buffer unless set by hand")
+                    .changed()
+                {
+                    config.output_latency_ms = manual.then_some(40);
+                    config_changed = true;
+                }
+                if let Some(latency) = &mut config.output_latency_ms
+                    && ui.add(egui::Slider::new(latency, 0..=500).suffix(" ms"))
+                        .on_hover_text("How long the sound device takes to play what it is given")
+                        .changed()
+                {
+                    config_changed = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Fade Out Button:");
+                if ui.add(egui::Slider::new(&mut config.fade_out_seconds, 1.0..=15.0).fixed_decimals(1).suffix(" s"))
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+
+            if ui.checkbox(&mut config.loudness_normalization, "Loudness normalization")
+                .on_hover_text("Play every song at the same loudness (applies from the next song loaded)")
+                .changed()
+            {
+                config_changed = true;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Target Loudness:");
+                if ui.add_enabled(
+                    config.loudness_normalization,
+                    egui::Slider::new(&mut config.loudness_target_lufs, -30.0..=-8.0)
+                        .fixed_decimals(1)
+                        .suffix(" LUFS"),
+                )
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+        });
+
+        ui.add_space(10.0);
+
         // Lyrics Display Section
         ui.group(|ui| {
             ui.label(egui::RichText::new("Lyrics Display").strong());
@@ -169,6 +267,95 @@ pub fn render(
 
         ui.add_space(10.0);
 
+        // Queue Section
+        ui.group(|ui| {
+            ui.label(egui::RichText::new("Queue").strong());
+            ui.add_space(5.0);
+
+            if ui.checkbox(&mut config.auto_advance, "Advance automatically")
+                .on_hover_text("When a song ends, show who's up next on the lyrics screen, then play their song")
+                .changed()
+            {
+                config_changed = true;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Up Next Screen:");
+                if ui.add_enabled(
+                    config.auto_advance,
+                    egui::Slider::new(&mut config.interstitial_seconds, 0.0..=60.0)
+                        .fixed_decimals(0)
+                        .suffix(" s"),
+                )
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Intermission Track:");
+                let track = config.intermission_track.as_deref().unwrap_or("None");
+                ui.label(track);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Crossfade:");
+                if ui.add_enabled(
+                    config.auto_advance && config.intermission_track.is_some(),
+                    egui::Slider::new(&mut config.crossfade_seconds, 0.0..=15.0)
+                        .fixed_decimals(1)
+                        .suffix(" s"),
+                )
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+            ui.label("(Intermission track is configured in config.toml)");
+        });
+
+        ui.add_space(10.0);
+
+        // Practice Section
+        ui.group(|ui| {
+            ui.label(egui::RichText::new("Practice").strong());
+            ui.add_space(5.0);
+
+            if ui.checkbox(&mut config.count_in, "Count in before each loop")
+                .on_hover_text("Click a count-in before every repetition of an A–B loop")
+                .changed()
+            {
+                config_changed = true;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Count-in Beats:");
+                if ui.add_enabled(config.count_in, egui::Slider::new(&mut config.count_in_beats, 1..=8))
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Default Tempo:");
+                if ui.add_enabled(
+                    config.count_in,
+                    egui::Slider::new(&mut config.count_in_bpm, 40.0..=240.0)
+                        .fixed_decimals(0)
+                        .suffix(" BPM"),
+                )
+                    .on_hover_text("Used for songs without a [bpm:] tag")
+                    .changed()
+                {
+                    config_changed = true;
+                }
+            });
+        });
+
+        ui.add_space(10.0);
+
         // Library Section
         ui.group(|ui| {
             ui.label(egui::RichText::new("Library").strong());